@@ -1,8 +1,381 @@
 #![deny(rust_2018_idioms)]
 
-#[cfg(feature = "generate_binding")]
-use std::path::PathBuf;
-use std::{env, fmt::Display, path::Path};
+use std::{
+    env,
+    ffi::OsStr,
+    fmt, fs, io,
+    path::{self, Path, PathBuf},
+    process::{self, Command},
+    sync::OnceLock,
+    time::Instant,
+};
+
+#[path = "build/linking.rs"]
+mod linking;
+#[cfg(any(unix, target_env = "gnu"))]
+#[path = "build/static_library.rs"]
+mod static_library;
+#[path = "build/target_settings.rs"]
+mod target_settings;
+
+/// Logs a detail of the build, see [`Verbosity`].
+macro_rules! info {
+    ($($argument:tt)*) => {
+        log(false, format_args!($($argument)*))
+    };
+}
+
+/// Logs a key decision of the build, such as where Opus comes from and how it
+/// is linked, see [`Verbosity`].
+macro_rules! decision {
+    ($($argument:tt)*) => {
+        log(true, format_args!($($argument)*))
+    };
+}
+
+/// How much the build script reports, set via `LIBOPUS_BUILD_VERBOSE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    /// Everything is logged as `cargo:info`, only shown by `cargo build -vv`.
+    Quiet,
+    /// Key decisions are promoted to warnings, shown by every build.
+    Normal,
+    /// Every detail is promoted to a warning.
+    Verbose,
+}
+
+impl Verbosity {
+    /// Reads the verbosity once, defaulting to [`Verbosity::Quiet`].
+    fn get() -> Self {
+        static VERBOSITY: OnceLock<Verbosity> = OnceLock::new();
+
+        *VERBOSITY.get_or_init(|| match env_var("LIBOPUS_BUILD_VERBOSE").as_deref() {
+            None | Some("quiet") => Self::Quiet,
+            Some("normal") => Self::Normal,
+            Some("verbose") => Self::Verbose,
+            Some(verbosity) => {
+                println!(
+                    "cargo:warning=Ignoring `LIBOPUS_BUILD_VERBOSE={}`, expected `quiet`, \
+                     `normal`, or `verbose`.",
+                    verbosity
+                );
+
+                Self::Quiet
+            }
+        })
+    }
+}
+
+/// Prints `message` as warning if the [`Verbosity`] asks for it, otherwise as
+/// `cargo:info`.
+fn log(is_decision: bool, message: fmt::Arguments<'_>) {
+    let promoting_verbosity = if is_decision {
+        Verbosity::Normal
+    } else {
+        Verbosity::Verbose
+    };
+
+    if Verbosity::get() >= promoting_verbosity {
+        println!("cargo:warning={}", message);
+    } else {
+        println!("cargo:info={}", message);
+    }
+}
+
+/// Errors aborting the build script.
+#[derive(Debug)]
+enum BuildError {
+    /// The `cmake` binary could not be executed.
+    CMakeNotFound(io::Error),
+    /// The bundled Opus source directory does not contain `CMakeLists.txt`,
+    /// usually because the Git submodule is not initialised.
+    MissingOpusSource(PathBuf),
+    /// The bundled Opus source directory is empty, but fetching it is
+    /// forbidden while offline.
+    OfflineWithoutOpusSource(PathBuf),
+    /// Running `git submodule update` failed.
+    InitSubmodule(io::Error),
+    /// Cloning the given Opus release failed.
+    CloneOpus(String, io::Error),
+    /// The Opus version set via `LIBOPUS_VERSION` is not among the releases
+    /// that may be cloned.
+    UnsupportedOpusVersion(String),
+    /// The Opus source is not the version set via `LIBOPUS_VERSION`, but
+    /// cloning it is not allowed, with the version found if known.
+    OpusVersionMismatch(&'static str, Option<String>),
+    /// The `dred`-feature is enabled, but the Opus source lacks the neural
+    /// network model data.
+    MissingDnnModel,
+    /// Downloading the neural network model data failed.
+    DownloadDnnModel(io::Error),
+    /// One of the `*_sources.mk` files listing Opus's sources could not be read.
+    ReadOpusSources(PathBuf, io::Error),
+    /// The Opus headers could not be copied next to the Opus built via `cc`.
+    CopyOpusHeaders(io::Error),
+    /// Compiling Opus via `cc` failed.
+    CompileOpus(cc::Error),
+    /// `meson` or `ninja` could not be run, with the reason why.
+    MesonUnavailable(String),
+    /// Configuring, compiling, or installing Opus via Meson failed.
+    BuildViaMeson(io::Error),
+    /// Cross-compiling for the target via Meson without a cross file.
+    MesonCrossFileRequired(String),
+    /// The Meson cross file set via `LIBOPUS_MESON_CROSS_FILE` does not exist.
+    MissingMesonCrossFile(PathBuf),
+    /// Installing Opus to the staging directory failed.
+    StageOpus(io::Error),
+    /// `LIBOPUS_STAGING_PREFIX` is set without `DESTDIR`, which would install
+    /// Opus to the final prefix at build time.
+    MissingDestdir(PathBuf),
+    /// The CMake toolchain file set via `LIBOPUS_CMAKE_TOOLCHAIN` does not exist.
+    MissingToolchainFile(PathBuf),
+    /// Neither `ANDROID_NDK_HOME` nor `ANDROID_NDK_ROOT` is set.
+    MissingAndroidNdk,
+    /// The Android target has no matching NDK ABI.
+    UnsupportedAndroidTarget(String),
+    /// `EMSDK` is not set while targeting Emscripten.
+    MissingEmsdk,
+    /// The CMake generator set via `LIBOPUS_CMAKE_GENERATOR` is not available,
+    /// with the reason why.
+    UnavailableGenerator(String, String),
+    /// The install prefix set via `LIBOPUS_INSTALL_PREFIX` lacks the given
+    /// part of an Opus installation.
+    InvalidInstallPrefix(PathBuf, &'static str),
+    /// The linked Opus is older than the pre-generated binding supports and
+    /// `LIBOPUS_STRICT_VERSION` is set.
+    OpusTooOld(String),
+    /// The library name set via `LIBOPUS_LINK_NAME` cannot be passed to
+    /// `rustc-link-lib`.
+    InvalidLinkName(String),
+    /// None of the checked directories contain an Opus library.
+    LibraryNotFound(Vec<PathBuf>),
+    /// Linking a probe against Opus failed, with the missing symbols if known
+    /// or otherwise the linker's output.
+    MissingSymbols(Vec<&'static str>, String),
+    /// The header to generate the binding from does not exist.
+    #[cfg(feature = "generate_binding")]
+    MissingWrapperHeader(PathBuf),
+    /// A library built on top of Opus, such as opusfile, could not be found.
+    #[cfg(feature = "generate_binding")]
+    CompanionLibraryNotFound(&'static CompanionLibrary),
+    /// `bindgen` failed to generate the binding.
+    #[cfg(feature = "generate_binding")]
+    GenerateBinding(bindgen::BindgenError),
+    /// The generated binding could not be written to the given path.
+    #[cfg(feature = "generate_binding")]
+    WriteBinding(PathBuf, io::Error),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CMakeNotFound(why) => write!(
+                f,
+                "Could not run `cmake` ({}), it is required to build Opus. {}",
+                why,
+                cmake_install_hint()
+            ),
+            Self::MissingOpusSource(path) => write!(
+                f,
+                "Could not find the Opus source at `{}`, if you cloned `audiopus_sys` via Git, \
+                 run `git submodule update --init --recursive` or set \
+                 `LIBOPUS_INIT_SUBMODULE` to do so automatically, otherwise set \
+                 `LIBOPUS_ALLOW_CLONE` to clone Opus",
+                path.display()
+            ),
+            Self::OfflineWithoutOpusSource(path) => write!(
+                f,
+                "Could not find the Opus source at `{}` and not fetching it while offline, \
+                 as set via `LIBOPUS_OFFLINE` or `--offline`. Run `git submodule update \
+                 --init --recursive` beforehand, or link a pre-installed Opus via \
+                 `LIBOPUS_LIB_DIR` or `pkg-config`",
+                path.display()
+            ),
+            Self::InitSubmodule(why) => {
+                write!(f, "Could not initialise the Opus submodule: {}", why)
+            }
+            Self::CloneOpus(tag, why) => write!(
+                f,
+                "Could not clone Opus {} from {}: {}",
+                tag, OPUS_CLONE_URL, why
+            ),
+            Self::UnsupportedOpusVersion(version) => write!(
+                f,
+                "`LIBOPUS_VERSION={}` is not a supported Opus release, expected one of {}",
+                version,
+                OPUS_RELEASES
+                    .iter()
+                    .map(|(release, _)| *release)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::OpusVersionMismatch(version, found) => write!(
+                f,
+                "The Opus source is {}, not {} as set via `LIBOPUS_VERSION`, set \
+                 `LIBOPUS_ALLOW_CLONE` to clone Opus {} instead",
+                found.as_deref().map_or_else(
+                    || String::from("of unknown version"),
+                    |found| format!("version {}", found)
+                ),
+                version,
+                version
+            ),
+            Self::MissingDnnModel => write!(
+                f,
+                "The `dred`-feature requires the Opus model data, which the Opus source lacks, \
+                 set `LIBOPUS_DOWNLOAD_MODEL` to download it"
+            ),
+            Self::DownloadDnnModel(why) => {
+                write!(f, "Could not download the Opus model data: {}", why)
+            }
+            Self::ReadOpusSources(path, why) => write!(
+                f,
+                "Could not read the Opus source list `{}`: {}",
+                path.display(),
+                why
+            ),
+            Self::CopyOpusHeaders(why) => write!(f, "Could not copy the Opus headers: {}", why),
+            Self::CompileOpus(why) => write!(f, "Could not compile Opus via `cc`: {}", why),
+            Self::StageOpus(why) => write!(
+                f,
+                "Could not install Opus to the staging directory set via \
+                 `LIBOPUS_STAGING_PREFIX` and `DESTDIR`: {}",
+                why
+            ),
+            Self::MissingDestdir(staging_prefix) => write!(
+                f,
+                "`LIBOPUS_STAGING_PREFIX` is set to `{}`, but `DESTDIR` is not. Set `DESTDIR` \
+                 to the staging root, Opus is not installed to the final prefix at build time",
+                staging_prefix.display()
+            ),
+            Self::MesonUnavailable(why) => write!(
+                f,
+                "Building Opus via Meson, as set via `LIBOPUS_BUILD_SYSTEM`, requires `meson` \
+                 and `ninja`: {}",
+                why
+            ),
+            Self::BuildViaMeson(why) => write!(f, "Could not build Opus via Meson: {}", why),
+            Self::MesonCrossFileRequired(target) => write!(
+                f,
+                "Building Opus via Meson for `{}` requires a cross file describing the target, \
+                 set `LIBOPUS_MESON_CROSS_FILE` to it, otherwise Meson builds for the host",
+                target
+            ),
+            Self::MissingMesonCrossFile(path) => write!(
+                f,
+                "Could not find the Meson cross file at `{}`",
+                path.display()
+            ),
+            Self::MissingToolchainFile(path) => write!(
+                f,
+                "Could not find the CMake toolchain file at `{}`",
+                path.display()
+            ),
+            Self::MissingAndroidNdk => write!(
+                f,
+                "Could not find the Android NDK, set `ANDROID_NDK_HOME` or `ANDROID_NDK_ROOT`"
+            ),
+            Self::UnsupportedAndroidTarget(target) => {
+                write!(f, "The Android target `{}` is not supported", target)
+            }
+            Self::MissingEmsdk => write!(
+                f,
+                "Building Opus for `wasm32-unknown-emscripten` requires Emscripten, activate it via \
+                 `source ./emsdk_env.sh` so that `EMSDK` is set"
+            ),
+            Self::UnavailableGenerator(generator, why) => write!(
+                f,
+                "The CMake generator `{}` set via `LIBOPUS_CMAKE_GENERATOR` is not available: {}",
+                generator, why
+            ),
+            Self::InvalidInstallPrefix(prefix, missing_part) => write!(
+                f,
+                "The install prefix `{}` does not contain {}",
+                prefix.display(),
+                missing_part
+            ),
+            Self::OpusTooOld(opus_version) => write!(
+                f,
+                "The linked Opus {} is older than Opus {}, the oldest the pre-generated \
+                 binding supports, unset `LIBOPUS_STRICT_VERSION` to link it anyway",
+                opus_version, BINDING_MIN_OPUS_VERSION
+            ),
+            Self::InvalidLinkName(link_name) => write!(
+                f,
+                "`{}`, set via `LIBOPUS_LINK_NAME`, is not a library name, expected a name like \
+                 `opus` without a path, `lib`-prefix, or extension",
+                link_name
+            ),
+            Self::LibraryNotFound(checked_dirs) => {
+                let checked_dirs = checked_dirs
+                    .iter()
+                    .map(|dir| format!("`{}`", dir.display()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                write!(
+                    f,
+                    "Could not find an Opus library, checked: {}",
+                    checked_dirs
+                )
+            }
+            Self::MissingSymbols(symbols, _) if !symbols.is_empty() => write!(
+                f,
+                "The linked Opus lacks symbols the binding declares: {}. Link a newer Opus or \
+                 disable the features requiring them",
+                symbols.join(", ")
+            ),
+            Self::MissingSymbols(_, linker_output) => write!(
+                f,
+                "Could not link a probe against Opus to verify its symbols: {}",
+                linker_output
+            ),
+            #[cfg(feature = "generate_binding")]
+            Self::MissingWrapperHeader(path) => write!(
+                f,
+                "Could not find the header to generate the binding from at `{}`, \
+                 check `LIBOPUS_WRAPPER_HEADER`",
+                path.display()
+            ),
+            #[cfg(feature = "generate_binding")]
+            Self::CompanionLibraryNotFound(library) => write!(
+                f,
+                "Could not find {}, install it so that `pkg-config` finds it or set `{}_LIB_DIR`",
+                library.name, library.env_prefix
+            ),
+            #[cfg(feature = "generate_binding")]
+            Self::GenerateBinding(why) => write!(f, "Unable to generate binding: {}", why),
+            #[cfg(feature = "generate_binding")]
+            Self::WriteBinding(path, why) => write!(
+                f,
+                "Could not write binding to the file at `{}`: {}",
+                path.display(),
+                why
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// The repository Opus is cloned from if `LIBOPUS_ALLOW_CLONE` is set.
+const OPUS_CLONE_URL: &str = "https://github.com/xiph/opus.git";
+
+/// The Opus release cloned if `LIBOPUS_ALLOW_CLONE` is set.
+const OPUS_CLONE_TAG: &str = "v1.5.2";
+
+/// The Opus releases `LIBOPUS_VERSION` may select, with the commit each
+/// release's tag must point to, guarding against a moved tag.
+///
+/// A release is only added along with its verified commit.
+const OPUS_RELEASES: &[(&str, &str)] = &[("1.5.2", "ddbe48383984d56acd9e1ab6a090c54ca6b735a6")];
+
+/// The Opus version `src/binding.rs` was generated for.
+const BINDING_OPUS_VERSION: &str = "1.5";
+
+/// The oldest Opus version declaring every item in `src/binding.rs` not guarded
+/// by a version `cfg`.
+const BINDING_MIN_OPUS_VERSION: &str = "1.3";
 
 /// Outputs the library-file's prefix as word usable for actual arguments on
 /// commands or paths.
@@ -10,13 +383,149 @@ const fn rustc_linking_word(is_static_link: bool) -> &'static str {
     if is_static_link { "static" } else { "dylib" }
 }
 
-/// Generates a new binding at `src/lib.rs` using `src/wrapper.h`.
+/// A library built on top of Opus, bound and linked next to Opus when its
+/// feature is enabled.
+#[cfg(feature = "generate_binding")]
+#[derive(Debug)]
+struct CompanionLibrary {
+    /// The name of the library as linked.
+    name: &'static str,
+    /// The name of the library's `pkg-config` package.
+    pkg_config_name: &'static str,
+    /// Whether the library's feature is enabled.
+    is_enabled: bool,
+    /// The prefix of the `_LIB_DIR` and `_INCLUDE_DIR` environment variables.
+    env_prefix: &'static str,
+    /// The preprocessor symbol making `src/wrapper.h` include its header.
+    wrapper_define: &'static str,
+    /// The libraries a static build of the library depends on, besides Opus.
+    static_dependencies: &'static [&'static str],
+    /// The patterns of the library's items kept in the binding.
+    allowlist: &'static [&'static str],
+}
+
+/// The libraries built on top of Opus that can be bound and linked.
+#[cfg(feature = "generate_binding")]
+const COMPANION_LIBRARIES: &[CompanionLibrary] = &[
+    CompanionLibrary {
+        name: "opusfile",
+        pkg_config_name: "opusfile",
+        is_enabled: cfg!(feature = "opusfile"),
+        env_prefix: "LIBOPUSFILE",
+        wrapper_define: "AUDIOPUS_OPUSFILE",
+        static_dependencies: &["ogg"],
+        allowlist: &["op_.*", "OP_.*", "OggOpusFile"],
+    },
+    CompanionLibrary {
+        name: "opusenc",
+        pkg_config_name: "libopusenc",
+        is_enabled: cfg!(feature = "opusenc"),
+        env_prefix: "LIBOPUSENC",
+        wrapper_define: "AUDIOPUS_OPUSENC",
+        static_dependencies: &[],
+        allowlist: &["ope_.*", "OPE_.*", "OggOpusEnc", "OggOpusComments"],
+    },
+];
+
+/// Finds and links `library`.
+///
+/// `<env_prefix>_LIB_DIR` points to a pre-installed library, otherwise
+/// `pkg-config` is used, whose result is added to `pkg_config_libraries` to
+/// be linked later. Returns the directories containing its headers.
+#[cfg(feature = "generate_binding")]
+fn find_companion_library(
+    library: &'static CompanionLibrary,
+    is_static: bool,
+    pkg_config_libraries: &mut Vec<pkg_config::Library>,
+) -> Result<Vec<PathBuf>, BuildError> {
+    let include_dir_override = env_var(&format!("{}_INCLUDE_DIR", library.env_prefix));
+
+    if let Some(installed_library) = env_var(&format!("{}_LIB_DIR", library.env_prefix)) {
+        let installed_library = PathBuf::from(installed_library);
+
+        let library_dirs = library_dir_candidates(&installed_library);
+
+        for library_dir in library_dirs.iter().filter(|dir| dir.is_dir()) {
+            println!("cargo:rustc-link-search=native={}", library_dir.display());
+        }
+
+        let link_kind = rustc_linking_word(is_static);
+        println!("cargo:rustc-link-lib={}={}", link_kind, library.name);
+
+        if is_static {
+            for dependency in library.static_dependencies {
+                println!("cargo:rustc-link-lib={}={}", link_kind, dependency);
+            }
+        }
+
+        let include_dir = include_dir_override.map_or_else(
+            || installed_library.join("include").join("opus"),
+            PathBuf::from,
+        );
+
+        return Ok(vec![include_dir]);
+    }
+
+    #[cfg(any(unix, target_env = "gnu"))]
+    if let Ok(mut libraries_found) = probe_pkg_config(&[library.pkg_config_name], is_static) {
+        let library_found = libraries_found.remove(0);
+        let mut include_dirs = library_found.include_paths.clone();
+        include_dirs.extend(include_dir_override.map(PathBuf::from));
+
+        pkg_config_libraries.push(library_found);
+
+        return Ok(include_dirs);
+    }
+
+    Err(BuildError::CompanionLibraryNotFound(library))
+}
+
+/// Finds and links every enabled [`CompanionLibrary`], before Opus, so that
+/// static libraries are linked in the order of their dependencies.
+///
+/// Those found via `pkg-config` are linked together, each library after all
+/// libraries depending on it, leaving Opus itself to be linked last.
+///
+/// Returns the directories containing their headers.
 #[cfg(feature = "generate_binding")]
-fn generate_binding() {
-    const ALLOW_UNCONVENTIONALS: &'static str = "#![allow(non_upper_case_globals)]\n\
-                                                 #![allow(non_camel_case_types)]\n\
-                                                 #![allow(non_snake_case)]\n";
+fn find_companion_libraries(is_static: bool) -> Result<Vec<PathBuf>, BuildError> {
+    let mut include_dirs = Vec::new();
+    let mut pkg_config_libraries = Vec::new();
+
+    for library in COMPANION_LIBRARIES
+        .iter()
+        .filter(|library| library.is_enabled)
+    {
+        let library_include_dirs =
+            find_companion_library(library, is_static, &mut pkg_config_libraries)?;
+
+        if let Some(include_dir) = library_include_dirs.first() {
+            println!("cargo:{}_include={}", library.name, include_dir.display());
+        }
+
+        include_dirs.extend(library_include_dirs);
+    }
+
+    link_pkg_config_libraries(&pkg_config_libraries, is_static, &["opus"]);
+
+    Ok(include_dirs)
+}
 
+/// Generates a new binding at `${OUT_DIR}/bindings.rs` using `src/wrapper.h`,
+/// which `src/lib.rs` includes instead of the pre-generated `src/binding.rs`.
+/// `LIBOPUS_WRAPPER_HEADER` replaces `src/wrapper.h`, e.g. to add opusfile.
+///
+/// The Opus headers are looked up in `include_dir`, the headers of enabled
+/// [`CompanionLibrary`]s in `companion_include_dirs`. The binding is formatted
+/// via `rustfmt` using `bindgen.rustfmt.toml`.
+///
+/// An included file cannot contain inner attributes, thus the lints for the
+/// unconventional C names are allowed on the `ffi`-module in `src/lib.rs` instead.
+#[cfg(feature = "generate_binding")]
+fn generate_binding(
+    include_dir: &Path,
+    companion_include_dirs: &[PathBuf],
+) -> Result<(), BuildError> {
     #[derive(Debug)]
     struct OpusCallbacks;
 
@@ -32,39 +541,67 @@ fn generate_binding() {
         }
     }
 
-    let bindings = bindgen::Builder::default()
-        .header("src/wrapper.h")
-        .raw_line(ALLOW_UNCONVENTIONALS)
+    let rustfmt_config = PathBuf::from(
+        env::var("CARGO_MANIFEST_DIR").expect("Cargo did not set `CARGO_MANIFEST_DIR`"),
+    )
+    .join("bindgen.rustfmt.toml");
+
+    let wrapper_header = env_var("LIBOPUS_WRAPPER_HEADER")
+        .map_or_else(|| PathBuf::from("src/wrapper.h"), PathBuf::from);
+
+    if !wrapper_header.is_file() {
+        return Err(BuildError::MissingWrapperHeader(wrapper_header));
+    }
+
+    println!("cargo:rerun-if-changed={}", wrapper_header.display());
+
+    let mut builder = bindgen::Builder::default();
+
+    for library in COMPANION_LIBRARIES
+        .iter()
+        .filter(|library| library.is_enabled)
+    {
+        builder = builder.clang_arg(format!("-D{}", library.wrapper_define));
+
+        for pattern in library.allowlist {
+            builder = builder.allowlist_item(pattern);
+        }
+    }
+
+    // Opus built without its float API lacks these functions.
+    if cfg!(feature = "no-float-api") {
+        builder = builder
+            .blocklist_function(".*_float")
+            .blocklist_function("opus_pcm_soft_clip");
+    }
+
+    let bindings = builder
+        .header(wrapper_header.to_string_lossy())
+        // Format with a pinned configuration to keep regenerating deterministic.
+        .formatter(bindgen::Formatter::Rustfmt)
+        .rustfmt_configuration_file(Some(rustfmt_config))
+        .clang_arg(format!("-I{}", include_dir.display()))
+        .clang_args(
+            companion_include_dirs
+                .iter()
+                .map(|companion_include_dir| format!("-I{}", companion_include_dir.display())),
+        )
+        .clang_args(cfg!(feature = "custom").then_some("-DAUDIOPUS_CUSTOM"))
         .parse_callbacks(Box::new(OpusCallbacks))
-        // Blocklist platform-specific types that aren't part of Opus API
-        .blocklist_type("_opaque_pthread_.*")
-        .blocklist_type("__darwin_.*")
-        .blocklist_type("__mbstate_t")
-        .blocklist_type("__int.*_t")
-        .blocklist_type("__uint.*_t")
-        // Blocklist platform-specific constants
-        .blocklist_item("__WORDSIZE")
-        .blocklist_item("__has_.*")
-        .blocklist_item("__DARWIN_.*")
-        .blocklist_item("_DARWIN_.*")
-        .blocklist_item("__STDC_.*")
-        .blocklist_item("USE_CLANG_TYPES")
-        .blocklist_item("__PTHREAD_.*")
-        .blocklist_item("INT.*_MAX")
-        .blocklist_item("INT.*_MIN")
-        .blocklist_item("UINT.*_MAX")
-        .blocklist_item("SIZE_MAX")
-        .blocklist_item("RSIZE_MAX")
-        .blocklist_item("WINT_.*")
-        .blocklist_item("SIG_ATOMIC_.*")
-        .blocklist_item("intmax_t")
-        .blocklist_item("uintmax_t")
-        .blocklist_item("__builtin_va_list")
-        // Blocklist platform-specific type aliases
-        .blocklist_type("int_least.*_t")
-        .blocklist_type("uint_least.*_t")
-        .blocklist_type("int_fast.*_t")
-        .blocklist_type("uint_fast.*_t")
+        // Refer to `core` only, so that the binding is usable without `std`.
+        .use_core()
+        .ctypes_prefix("::core::ffi")
+        .size_t_is_usize(true)
+        // Opus' structs are all opaque, a `Default` would allow creating them
+        // without Opus.
+        .derive_debug(true)
+        .derive_copy(true)
+        .derive_default(true)
+        .no_default("Opus.*")
+        // Only emit Opus' own items, whatever the system headers declare.
+        .allowlist_function("opus_.*")
+        .allowlist_type("Opus.*")
+        .allowlist_var("OPUS_.*")
         // Blocklist extended opus types that aren't in the core API
         .blocklist_item("opus_int8")
         .blocklist_item("opus_uint8")
@@ -80,143 +617,3849 @@ fn generate_binding() {
         .raw_line("pub type opus_int32 = i32;")
         .raw_line("pub type opus_uint32 = u32;")
         .generate()
-        .expect("Unable to generate binding");
+        .map_err(BuildError::GenerateBinding)?;
 
-    let binding_target_path = PathBuf::new().join("src").join("lib.rs");
+    let binding_target_path =
+        PathBuf::from(env::var("OUT_DIR").expect("Cargo did not set `OUT_DIR`"))
+            .join("bindings.rs");
 
     bindings
-        .write_to_file(binding_target_path)
-        .expect("Could not write binding to the file at `src/lib.rs`");
+        .write_to_file(&binding_target_path)
+        .map_err(|why| BuildError::WriteBinding(binding_target_path, why))?;
 
-    println!("cargo:info=Successfully generated binding.");
+    info!("Successfully generated binding.");
+
+    Ok(())
 }
 
-fn build_opus(is_static: bool) {
-    let opus_path = Path::new("opus");
+/// Copies the pre-generated `src/binding.rs` to `${OUT_DIR}/bindings.rs`,
+/// where `src/lib.rs` expects the generated binding.
+///
+/// Used when not linking Opus, where neither the companion libraries nor
+/// libclang are required. The binding then lacks their declarations.
+#[cfg(feature = "generate_binding")]
+fn copy_pregenerated_binding() -> Result<(), BuildError> {
+    let binding_target_path =
+        PathBuf::from(env::var("OUT_DIR").expect("Cargo did not set `OUT_DIR`"))
+            .join("bindings.rs");
 
-    println!(
-        "cargo:info=Opus source path used: {:?}.",
-        opus_path
-            .canonicalize()
-            .expect("Could not canonicalise to absolute path")
+    fs::copy("src/binding.rs", &binding_target_path)
+        .map_err(|why| BuildError::WriteBinding(binding_target_path, why))?;
+
+    info!("Using the pre-generated binding.");
+
+    Ok(())
+}
+
+/// Reads the target's `cfg`-value `name`, e.g. `target_os`.
+///
+/// The build script is compiled for the host, thus `cfg!` describes the host
+/// rather than the target.
+fn target_cfg(name: &str) -> String {
+    env::var(format!("CARGO_CFG_{}", name.to_uppercase())).unwrap_or_default()
+}
+
+/// Returns whether the target feature `feature` is enabled, e.g. `crt-static`.
+fn has_target_feature(feature: &str) -> bool {
+    target_cfg("target_feature")
+        .split(',')
+        .any(|enabled_feature| enabled_feature == feature)
+}
+
+/// Every environment variable the build script reads, besides those set by
+/// Cargo itself, with a description, as listed via `LIBOPUS_HELP`.
+///
+/// `env_var` asserts that every `LIBOPUS_`- and `OPUS_`-variable it reads is
+/// listed here.
+const ENV_VARS: &[(&str, &str)] = &[
+    ("LIBOPUS_HELP", "lists these environment variables"),
+    (
+        "LIBOPUS_STATIC",
+        "links Opus statically, overriding the features",
+    ),
+    ("OPUS_STATIC", "same as `LIBOPUS_STATIC`"),
+    (
+        "LIBOPUS_DYNAMIC",
+        "links Opus dynamically, overriding the features",
+    ),
+    ("OPUS_DYNAMIC", "same as `LIBOPUS_DYNAMIC`"),
+    (
+        "LIBOPUS_NO_LINK",
+        "only compiles the declarations, without linking Opus",
+    ),
+    (
+        "LIBOPUS_NO_PKG",
+        "skips discovering Opus via `pkg-config`, vcpkg, and package managers",
+    ),
+    ("OPUS_NO_PKG", "same as `LIBOPUS_NO_PKG`"),
+    (
+        "LIBOPUS_MIN_VERSION",
+        "ignores an older Opus found via `pkg-config`",
+    ),
+    ("LIBOPUS_LIB_DIR", "directory of a pre-installed Opus"),
+    ("OPUS_LIB_DIR", "same as `LIBOPUS_LIB_DIR`"),
+    ("LIBOPUS_INCLUDE_DIR", "directory containing `opus.h`"),
+    ("OPUS_INCLUDE_DIR", "same as `LIBOPUS_INCLUDE_DIR`"),
+    (
+        "LIBOPUS_INSTALL_PREFIX",
+        "install prefix of a pre-installed Opus",
+    ),
+    ("OPUS_INSTALL_PREFIX", "same as `LIBOPUS_INSTALL_PREFIX`"),
+    (
+        "LIBOPUS_LINK_NAME",
+        "name of the library to link instead of `opus`",
+    ),
+    ("LIBOPUS_LIB_NAME", "same as `LIBOPUS_LINK_NAME`"),
+    ("LIBOPUS_FRAMEWORK", "`Opus.framework` to link on macOS"),
+    (
+        "LIBOPUS_WHOLE_ARCHIVE",
+        "links a static Opus as whole archive",
+    ),
+    (
+        "LIBOPUS_SET_RPATH",
+        "embeds a run-time search path for a dynamic Opus",
+    ),
+    (
+        "LIBOPUS_VERIFY_SYMBOLS",
+        "links a probe against Opus to verify the binding's symbols",
+    ),
+    (
+        "LIBOPUS_STRICT_VERSION",
+        "fails if Opus is older than the binding supports",
+    ),
+    (
+        "LIBOPUS_WRAPPER_HEADER",
+        "header to generate the binding from",
+    ),
+    (
+        "LIBOPUSFILE_LIB_DIR",
+        "directory of a pre-installed opusfile",
+    ),
+    (
+        "LIBOPUSFILE_INCLUDE_DIR",
+        "directory containing the opusfile headers",
+    ),
+    (
+        "LIBOPUSENC_LIB_DIR",
+        "directory of a pre-installed libopusenc",
+    ),
+    (
+        "LIBOPUSENC_INCLUDE_DIR",
+        "directory containing the libopusenc headers",
+    ),
+    (
+        "LIBOPUS_INIT_SUBMODULE",
+        "initialises the Opus submodule if missing",
+    ),
+    (
+        "LIBOPUS_ALLOW_CLONE",
+        "clones Opus if its source is missing",
+    ),
+    (
+        "LIBOPUS_VERSION",
+        "selects the Opus release to build, cloned if it differs from the source",
+    ),
+    ("LIBOPUS_OFFLINE", "forbids any network access"),
+    (
+        "LIBOPUS_DOWNLOAD_MODEL",
+        "downloads the model data for the `dred`-feature",
+    ),
+    (
+        "LIBOPUS_BUILD_SYSTEM",
+        "builds Opus via `cmake`, `meson`, or `cc`",
+    ),
+    (
+        "LIBOPUS_MESON_CROSS_FILE",
+        "Meson cross file describing the target when cross-compiling",
+    ),
+    ("LIBOPUS_CACHE_DIR", "directory to cache built Opus in"),
+    (
+        "LIBOPUS_STAGING_PREFIX",
+        "final prefix to install the built Opus to, staged below `DESTDIR`",
+    ),
+    (
+        "LIBOPUS_CMAKE_BUILD_TYPE",
+        "CMake build type, e.g. `Release`",
+    ),
+    ("LIBOPUS_CMAKE_GENERATOR", "CMake generator, e.g. `Ninja`"),
+    ("LIBOPUS_CMAKE_TOOLCHAIN", "CMake toolchain file"),
+    ("OPUS_CMAKE_TOOLCHAIN", "same as `LIBOPUS_CMAKE_TOOLCHAIN`"),
+    (
+        "LIBOPUS_CMAKE_DEFINES",
+        "additional `KEY=VALUE` definitions for CMake",
+    ),
+    (
+        "LIBOPUS_COMPILER_LAUNCHER",
+        "compiler cache, e.g. `sccache`",
+    ),
+    ("LIBOPUS_CLANG_CL", "compiles Opus via clang-cl on MSVC"),
+    (
+        "LIBOPUS_ASSERTIONS",
+        "turns Opus's assertions `ON` or `OFF`",
+    ),
+    ("LIBOPUS_HARDENING", "turns Opus's hardening `ON` or `OFF`"),
+    (
+        "LIBOPUS_INTRINSICS",
+        "`off` or `target` to control CPU intrinsics",
+    ),
+    (
+        "LIBOPUS_NEON",
+        "`presume`, `runtime`, or `off` to control NEON",
+    ),
+    ("LIBOPUS_PIC", "builds Opus as position-independent code"),
+    (
+        "LIBOPUS_CDYLIB",
+        "hints that Opus is linked into a `cdylib`, requiring position-independent code",
+    ),
+    (
+        "LIBOPUS_LTO",
+        "builds a static Opus with link-time optimisation",
+    ),
+    ("LIBOPUS_STRIP", "strips a static Opus built for release"),
+    (
+        "LIBOPUS_REPRODUCIBLE",
+        "builds Opus without timestamps and absolute paths",
+    ),
+    (
+        "LIBOPUS_MACOS_ARCHS",
+        "architectures of a universal macOS Opus",
+    ),
+    (
+        "LIBOPUS_BUILD_VERBOSE",
+        "`normal` or `verbose` to show build steps",
+    ),
+    (
+        "LIBOPUS_PRINT_CONFIG",
+        "prints the resolved build configuration",
+    ),
+    (
+        "CC",
+        "C compiler, also read as `CC_<target>` and `TARGET_CC`",
+    ),
+    (
+        "CFLAGS",
+        "C flags, also read as `CFLAGS_<target>` and `TARGET_CFLAGS`",
+    ),
+    (
+        "STRIP",
+        "`strip` binary, also read as `STRIP_<target>` and `TARGET_STRIP`",
+    ),
+    ("CMAKE", "CMake binary"),
+    (
+        "DESTDIR",
+        "staging root `LIBOPUS_STAGING_PREFIX` is installed below",
+    ),
+    (
+        "CMAKE_GENERATOR",
+        "CMake generator used by the `cmake`-crate",
+    ),
+    (
+        "RUSTC_WRAPPER",
+        "reused as compiler launcher if `sccache` or `ccache`",
+    ),
+    (
+        "CARGO_NET_OFFLINE",
+        "set by Cargo's `--offline`, forbids network access",
+    ),
+    ("SOURCE_DATE_EPOCH", "timestamp of reproducible builds"),
+    (
+        "MACOSX_DEPLOYMENT_TARGET",
+        "oldest macOS version to support",
+    ),
+    ("ANDROID_NDK_HOME", "Android NDK to build Opus with"),
+    ("ANDROID_NDK_ROOT", "same as `ANDROID_NDK_HOME`"),
+    (
+        "ANDROID_PLATFORM",
+        "minimum Android API level, e.g. `android-24`",
+    ),
+    ("EMSDK", "Emscripten SDK to build Opus for WebAssembly with"),
+    ("VCPKG_ROOT", "vcpkg installation to look for Opus in"),
+    (
+        "VCPKGRS_TRIPLET",
+        "vcpkg triplet overriding the `vcpkg`-crate's choice",
+    ),
+    ("VCPKGRS_DYNAMIC", "lets vcpkg link Opus dynamically"),
+    ("DOCS_RS", "set on docs.rs, where Opus is not linked"),
+];
+
+/// Reads the environment variable `name` and tells Cargo to re-run the build
+/// script whenever it changes.
+///
+/// Every environment variable the build script inspects must be read via this
+/// function, otherwise changing it will not trigger a rebuild. Variables set by
+/// Cargo itself, such as `OUT_DIR`, are exempt.
+fn env_var(name: &str) -> Option<String> {
+    debug_assert!(
+        !(name.starts_with("LIBOPUS_") || name.starts_with("OPUS_"))
+            || ENV_VARS.iter().any(|(listed_name, _)| *listed_name == name),
+        "`{}` is missing from `ENV_VARS`",
+        name
     );
 
-    println!("cargo:info=Building Opus via CMake.");
-    let mut config = cmake::Config::new(opus_path);
+    println!("cargo:rerun-if-env-changed={}", name);
+
+    env::var(name).ok()
+}
+
+/// Reads a setting that can be spelled with either prefix, `LIBOPUS_<name>`
+/// or `OPUS_<name>`.
+///
+/// `LIBOPUS_<name>` takes precedence. If both are set to different values, a
+/// warning is emitted. Every setting read with both prefixes must be read via
+/// this function.
+fn opus_env_var(name: &str) -> Option<String> {
+    let libopus_name = format!("LIBOPUS_{}", name);
+    let opus_name = format!("OPUS_{}", name);
+
+    match (env_var(&libopus_name), env_var(&opus_name)) {
+        (Some(libopus_value), Some(opus_value)) => {
+            if libopus_value != opus_value {
+                println!(
+                    "cargo:warning=Both `{}={}` and `{}={}` are set, using `{}`.",
+                    libopus_name, libopus_value, opus_name, opus_value, libopus_name
+                );
+            }
+
+            Some(libopus_value)
+        }
+        (libopus_value, opus_value) => libopus_value.or(opus_value),
+    }
+}
+
+/// Reads the environment variable `name` as a boolean flag, accepting
+/// `1`/`0`, `on`/`off`, `true`/`false`, and `yes`/`no`.
+///
+/// Unrecognised values are ignored with a warning.
+fn env_flag(name: &str) -> Option<bool> {
+    let value = env_var(name)?;
+
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "on" | "true" | "yes" => Some(true),
+        "0" | "off" | "false" | "no" => Some(false),
+        _ => {
+            println!(
+                "cargo:warning=Ignoring `{}={}`, expected one of `1`, `0`, `on`, `off`, \
+                 `true`, `false`, `yes`, or `no`.",
+                name, value
+            );
+
+            None
+        }
+    }
+}
+
+/// Formats `value` as CMake boolean.
+const fn cmake_bool(value: bool) -> &'static str {
+    if value { "ON" } else { "OFF" }
+}
+
+/// Lists every environment variable in [`ENV_VARS`] with its current value,
+/// if `LIBOPUS_HELP` is set.
+fn print_env_help() {
+    if env_var("LIBOPUS_HELP").is_none() {
+        return;
+    }
+
+    println!("cargo:warning=Environment variables read by `audiopus_sys`:");
+
+    for (name, description) in ENV_VARS {
+        // Only displayed, thus not read via `env_var`.
+        let value =
+            env::var(name).map_or_else(|_| String::from("unset"), |value| format!("`{}`", value));
+
+        println!("cargo:warning=  {}: {} ({})", name, description, value);
+    }
+}
+
+/// Tells Cargo which files should cause the build script to run again.
+///
+/// Without any of these directives, Cargo re-runs the script whenever any
+/// file in the package changes, which makes CMake reconsider the whole Opus
+/// tree. Directories are scanned recursively by Cargo, so watching `opus`
+/// covers `CMakeLists.txt` as well as every C source and header.
+fn emit_rerun_if_changed() {
+    const BUILD_INPUTS: &[&str] = &["build.rs", "src/wrapper.h", "bindgen.rustfmt.toml", "opus"];
 
-    if true {
-        // Always build Opus in release mode to avoid debug CRT dependency on Windows.
-        // Rust defaults to release CRT even in debug builds, but CMake defaults to debug CRT,
-        // causing linker errors when consumers build in debug mode.
-        config.profile("Release");
+    for build_input in BUILD_INPUTS {
+        println!("cargo:rerun-if-changed={}", build_input);
+    }
+}
+
+/// Returns a suggestion on how to install CMake on the host building Opus.
+fn cmake_install_hint() -> &'static str {
+    if cfg!(windows) {
+        "Install it via `choco install cmake` or from https://cmake.org/download/ \
+         and make sure it is on your `PATH`."
+    } else if cfg!(target_os = "macos") {
+        "Install it via `brew install cmake` or `port install cmake`."
+    } else if cfg!(target_os = "freebsd") {
+        "Install it via `pkg install cmake`."
     } else {
-        // Disable assertions and hardening to avoid debug CRT dependency on Windows
-        // Rust defaults to release CRT even in debug builds, but CMake defaults to debug CRT
-        config.define("OPUS_ASSERTIONS", "OFF");
-        config.define("OPUS_HARDENING", "OFF");
+        "Install it via your package manager, e.g. `apt install cmake` or \
+         `dnf install cmake`."
+    }
+}
 
-        // Also define NO_ASSERTS to disable SILK assertions that use _ASSERTE on Windows
-        // which requires the debug CRT (__imp__CrtDbgReportW)
-        config.cflag("-DNO_ASSERTS");
+/// Returns the CMake binary to run.
+///
+/// Like the `cmake` crate, this respects the `CMAKE` environment variable.
+fn cmake_binary() -> String {
+    env_var("CMAKE").unwrap_or_else(|| String::from("cmake"))
+}
+
+/// Runs `cmake --version` and returns the reported version.
+fn detect_cmake_version() -> Result<String, BuildError> {
+    let output = Command::new(cmake_binary())
+        .arg("--version")
+        .output()
+        .map_err(BuildError::CMakeNotFound)?;
+
+    // The first line reads `cmake version 3.28.3`.
+    let version = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .and_then(|line| line.rsplit(' ').next())
+        .unwrap_or("unknown")
+        .to_string();
+
+    Ok(version)
+}
+
+/// Checks whether CMake knows `generator` and, for Ninja-based generators,
+/// whether `ninja` can be run.
+fn check_generator(generator: &str) -> Result<(), BuildError> {
+    let unavailable = |why: String| BuildError::UnavailableGenerator(generator.to_string(), why);
+
+    let output = Command::new(cmake_binary())
+        .arg("--help")
+        .output()
+        .map_err(BuildError::CMakeNotFound)?;
+
+    // Generators are listed as `  Ninja  = Generates build.ninja files.`, the
+    // default one is marked with `*` and some carry an optional `[arch]`.
+    let help = String::from_utf8_lossy(&output.stdout);
+    let is_known = help
+        .lines()
+        .skip_while(|line| !line.starts_with("Generators"))
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, _)| name.trim_start_matches([' ', '*']).trim_end())
+        .any(|name| name.trim_end_matches("[arch]").trim_end() == generator);
+
+    if !is_known {
+        return Err(unavailable(String::from("`cmake --help` does not list it")));
     }
 
-    let opus_build_dir = config.build();
-    link_opus(is_static, opus_build_dir.display())
+    if generator.contains("Ninja") {
+        Command::new("ninja")
+            .arg("--version")
+            .output()
+            .map_err(|why| unavailable(format!("could not run `ninja` ({})", why)))?;
+    }
+
+    Ok(())
 }
 
-fn link_opus(is_static: bool, opus_build_dir: impl Display) {
-    let is_static_text = rustc_linking_word(is_static);
+/// A `cmake::Config` recording every setting passed to it, so that the cache
+/// key covers exactly what configures the CMake build.
+struct CMakeConfig {
+    config: cmake::Config,
+    settings: Vec<String>,
+}
 
-    println!(
-        "cargo:info=Linking Opus as {} lib: {}",
-        is_static_text, opus_build_dir
-    );
-    println!("cargo:rustc-link-lib={}=opus", is_static_text);
-    println!("cargo:rustc-link-search=native={}/lib", opus_build_dir);
+impl CMakeConfig {
+    fn new(opus_path: &Path) -> Self {
+        Self {
+            config: cmake::Config::new(opus_path),
+            settings: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, kind: &str, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) {
+        self.settings.push(format!(
+            "{} {}={}",
+            kind,
+            key.as_ref().to_string_lossy(),
+            value.as_ref().to_string_lossy()
+        ));
+    }
+
+    fn profile(&mut self, profile: &str) -> &mut Self {
+        self.record("profile", "", profile);
+        self.config.profile(profile);
+        self
+    }
+
+    fn define(&mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.record("define", &key, &value);
+        self.config.define(key, value);
+        self
+    }
+
+    fn cflag(&mut self, flag: impl AsRef<OsStr>) -> &mut Self {
+        self.record("cflag", "", &flag);
+        self.config.cflag(flag);
+        self
+    }
+
+    fn env(&mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.record("env", &key, &value);
+        self.config.env(key, value);
+        self
+    }
+
+    fn generator(&mut self, generator: impl AsRef<OsStr>) -> &mut Self {
+        self.record("generator", "", &generator);
+        self.config.generator(generator);
+        self
+    }
+
+    fn generator_toolset(&mut self, toolset: impl AsRef<OsStr>) -> &mut Self {
+        self.record("generator-toolset", "", &toolset);
+        self.config.generator_toolset(toolset);
+        self
+    }
+
+    fn target(&mut self, target: &str) -> &mut Self {
+        self.record("target", "", target);
+        self.config.target(target);
+        self
+    }
+
+    fn build(&mut self) -> PathBuf {
+        self.config.build()
+    }
 }
 
-#[cfg(any(unix, target_env = "gnu"))]
-fn find_via_pkg_config(is_static: bool) -> bool {
-    pkg_config::Config::new()
-        .statik(is_static)
-        .probe("opus")
-        .is_ok()
+/// Uses the CMake generator set via `LIBOPUS_CMAKE_GENERATOR`, e.g. `Ninja`,
+/// instead of CMake's default.
+///
+/// Otherwise, on hosts other than Windows and unless `CMAKE_GENERATOR` is set,
+/// Ninja is preferred if found on `PATH`, then Make, so that images providing
+/// only one of them build Opus. On Windows, the `cmake` crate and
+/// [`configure_mingw`] pick the generator.
+fn configure_generator(config: &mut CMakeConfig) -> Result<(), BuildError> {
+    if let Some(generator) = env_var("LIBOPUS_CMAKE_GENERATOR") {
+        check_generator(&generator)?;
+
+        info!("Using CMake generator {}.", generator);
+        config.generator(generator);
+
+        return Ok(());
+    }
+
+    if cfg!(windows) || env_var("CMAKE_GENERATOR").is_some() {
+        return Ok(());
+    }
+
+    // CMake refuses to switch the generator of an existing build directory.
+    if let Some(generator) = previous_cmake_generator() {
+        info!("Using CMake generator {} of the previous build.", generator);
+        config.generator(generator);
+    } else if find_in_path("ninja").is_some() {
+        info!("Using CMake generator Ninja, as `ninja` was found.");
+        config.generator("Ninja");
+    } else if find_in_path("make").is_some() {
+        info!("Using CMake generator Unix Makefiles, as `make` was found.");
+        config.generator("Unix Makefiles");
+    } else {
+        println!(
+            "cargo:warning=Found neither `ninja` nor `make` on `PATH`, building Opus via CMake \
+             will likely fail. Install either or set `LIBOPUS_CMAKE_GENERATOR`."
+        );
+    }
+
+    Ok(())
 }
 
-/// Based on the OS or target environment we are building for,
-/// this function will return an expected default library linking method.
+/// Returns the generator recorded in the `CMakeCache.txt` of a previous
+/// build, e.g. `CMAKE_GENERATOR:INTERNAL=Ninja`.
+fn previous_cmake_generator() -> Option<String> {
+    let out_dir = env::var_os("OUT_DIR")?;
+    let cmake_cache =
+        fs::read_to_string(Path::new(&out_dir).join("build").join("CMakeCache.txt")).ok()?;
+
+    cmake_cache
+        .lines()
+        .find_map(|line| line.strip_prefix("CMAKE_GENERATOR:INTERNAL="))
+        .map(str::to_string)
+}
+
+/// Prepares building Opus for the MinGW toolchain, i.e. the `windows-gnu`
+/// targets.
 ///
-/// If we build for Windows, MacOS, or Linux with musl, we will link statically.
-/// However, if you build for Linux without musl, we will link dynamically.
+/// Unless `LIBOPUS_CMAKE_GENERATOR` is set, Ninja is preferred on Windows if
+/// available, otherwise the `cmake` crate picks MinGW's or MSYS's Makefiles.
+fn configure_mingw(config: &mut CMakeConfig) {
+    if target_cfg("target_os") != "windows" || target_cfg("target_env") != "gnu" {
+        return;
+    }
+
+    if cfg!(windows) {
+        if env_var("LIBOPUS_CMAKE_GENERATOR").is_none() && find_in_path("ninja").is_some() {
+            info!("Using CMake generator Ninja for MinGW.");
+            config.generator("Ninja");
+        }
+
+        if target_env_var("CC").is_none() && find_in_path("gcc").is_none() {
+            println!(
+                "cargo:warning=Could not find MinGW's `gcc` on `PATH`, which is required to \
+                 build Opus for the `windows-gnu` target."
+            );
+        }
+    }
+}
+
+/// Picks the CMake build type matching Cargo's optimisation level and, for
+/// optimised builds, whether debug info is enabled, e.g. to profile Opus.
 ///
-/// **Info**:
-/// This is a helper-function and may not be called if
-/// if the `static`-feature is enabled, the environment variable
-/// `LIBOPUS_STATIC` or `OPUS_STATIC` is set.
-fn default_library_linking() -> bool {
-    #[cfg(any(windows, target_os = "macos", target_env = "musl"))]
-    {
-        true
+/// `LIBOPUS_CMAKE_BUILD_TYPE` takes precedence over the optimisation level.
+fn cmake_build_type() -> String {
+    if let Some(build_type) = env_var("LIBOPUS_CMAKE_BUILD_TYPE") {
+        return build_type;
     }
-    #[cfg(any(target_os = "freebsd", all(unix, target_env = "gnu")))]
-    {
-        false
+
+    let has_debug_info = env::var("DEBUG").is_ok_and(|debug| debug == "true");
+
+    match env::var("OPT_LEVEL").as_deref() {
+        Ok("s" | "z") => "MinSizeRel",
+        Ok("0") | Err(_) => "Debug",
+        Ok(_) if has_debug_info => "RelWithDebInfo",
+        Ok(_) => "Release",
     }
+    .to_string()
 }
 
-fn find_installed_opus() -> Option<String> {
-    if let Ok(lib_directory) = env::var("LIBOPUS_LIB_DIR") {
-        Some(lib_directory)
-    } else if let Ok(lib_directory) = env::var("OPUS_LIB_DIR") {
-        Some(lib_directory)
-    } else {
-        None
+/// Parses semicolon-separated `KEY=VALUE` pairs, e.g.
+/// `OPUS_ENABLE_FLOAT_API=OFF;OPUS_X86_MAY_HAVE_AVX=OFF`.
+///
+/// Malformed entries are skipped with a warning.
+fn parse_cmake_defines(cmake_defines: &str) -> Vec<(&str, &str)> {
+    cmake_defines
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| match entry.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Some((key.trim(), value.trim())),
+            _ => {
+                println!(
+                    "cargo:warning=Ignoring malformed entry `{}` in `LIBOPUS_CMAKE_DEFINES`, \
+                     expected `KEY=VALUE`.",
+                    entry
+                );
+
+                None
+            }
+        })
+        .collect()
+}
+
+/// Reads the environment variable `name` the way the `cc`-crate does, looking
+/// for target-specific variants first, e.g. `CC_aarch64_linux_android`,
+/// `TARGET_CC`, then `CC`.
+fn target_env_var(name: &str) -> Option<String> {
+    let target = env::var("TARGET").unwrap_or_default();
+
+    env_var(&format!("{}_{}", name, target))
+        .or_else(|| env_var(&format!("{}_{}", name, target.replace('-', "_"))))
+        .or_else(|| env_var(&format!("TARGET_{}", name)))
+        .or_else(|| env_var(name))
+}
+
+/// Hands the C compiler configured via `CC` and friends to CMake, so that
+/// cross-compiling does not fall back to the host compiler.
+fn configure_compiler(config: &mut CMakeConfig) {
+    let target = env::var("TARGET").unwrap_or_default();
+    let is_cross_compiling = env::var("HOST").is_ok_and(|host| host != target);
+
+    let compiler = target_env_var("CC");
+    let is_clang_cl = configure_clang_cl(config, compiler.as_deref());
+
+    // clang-cl has been configured already.
+    if let Some(compiler) = compiler.filter(|_| !is_clang_cl) {
+        info!("Using C compiler `{}`.", compiler);
+
+        // Clang is a cross-compiler by itself, but needs to know the target.
+        if is_cross_compiling && compiler.contains("clang") {
+            config.define("CMAKE_C_COMPILER_TARGET", &target);
+        }
+
+        config.define("CMAKE_C_COMPILER", compiler);
     }
+
+    // The `cmake`-crate already passes these flags via `CMAKE_C_FLAGS`, as it
+    // resolves them through the `cc`-crate, adding them again would duplicate
+    // them.
+    if let Some(cflags) = target_env_var("CFLAGS") {
+        info!("Using C flags `{}`.", cflags);
+    }
+
+    config.target(&target);
 }
 
-fn is_static_build() -> bool {
-    if cfg!(feature = "static") && cfg!(feature = "dynamic") {
-        default_library_linking()
-    } else if cfg!(feature = "static")
-        || env::var("LIBOPUS_STATIC").is_ok()
-        || env::var("OPUS_STATIC").is_ok()
-    {
-        println!("cargo:info=Static feature or environment variable found.");
+/// Compiles Opus via clang-cl when targeting MSVC, if `CC` names `clang-cl`
+/// or `LIBOPUS_CLANG_CL` is set.
+///
+/// The Visual Studio generators, CMake's default for MSVC, pick clang-cl via
+/// the `ClangCL` toolset, others via `CMAKE_C_COMPILER`. The MSVC runtime
+/// library is still selected via `CMAKE_MSVC_RUNTIME_LIBRARY`, which clang-cl
+/// honours as well. Returns whether clang-cl is used.
+fn configure_clang_cl(config: &mut CMakeConfig, compiler: Option<&str>) -> bool {
+    if target_cfg("target_env") != "msvc" {
+        return false;
+    }
+
+    let is_clang_cl_compiler = compiler.is_some_and(|compiler| {
+        Path::new(compiler)
+            .file_stem()
+            .is_some_and(|name| name.eq_ignore_ascii_case("clang-cl"))
+    });
 
-        true
-    } else if cfg!(feature = "dynamic") {
-        println!("cargo:info=Dynamic feature enabled.");
+    if !is_clang_cl_compiler && !env_flag("LIBOPUS_CLANG_CL").unwrap_or(false) {
+        return false;
+    }
+
+    let generator = env_var("LIBOPUS_CMAKE_GENERATOR").or_else(|| env_var("CMAKE_GENERATOR"));
 
-        false
+    if generator.is_none_or(|generator| generator.starts_with("Visual Studio")) {
+        info!("Using the ClangCL toolset.");
+        config.generator_toolset("ClangCL");
     } else {
-        println!("cargo:info=No feature or environment variable found, linking by default.");
+        let compiler = compiler
+            .filter(|_| is_clang_cl_compiler)
+            .unwrap_or("clang-cl");
 
-        default_library_linking()
+        info!("Using C compiler `{}`.", compiler);
+        config.define("CMAKE_C_COMPILER", compiler);
     }
+
+    true
 }
 
-fn main() {
-    #[cfg(feature = "generate_binding")]
-    generate_binding();
+/// Searches `PATH` for `binary`, unless it already is a path to a file.
+fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let binary_path = Path::new(binary);
 
-    let is_static = is_static_build();
+    if binary_path.components().count() > 1 {
+        return binary_path.is_file().then(|| binary_path.to_path_buf());
+    }
 
-    #[cfg(any(unix, target_env = "gnu"))]
-    {
-        if std::env::var("LIBOPUS_NO_PKG").is_ok() || std::env::var("OPUS_NO_PKG").is_ok() {
-            println!("cargo:info=Bypassed `pkg-config`.");
-        } else if find_via_pkg_config(is_static) {
-            println!("cargo:info=Found `Opus` via `pkg_config`.");
+    let path = env::var_os("PATH")?;
 
-            return;
-        } else {
-            println!("cargo:info=`pkg_config` could not find `Opus`.");
+    env::split_paths(&path)
+        .flat_map(|dir| {
+            let candidate = dir.join(binary);
+            let executable = candidate.with_extension(env::consts::EXE_EXTENSION);
+
+            [candidate, executable]
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+/// Wraps the C compiler in a compiler cache via `CMAKE_C_COMPILER_LAUNCHER`.
+///
+/// `LIBOPUS_COMPILER_LAUNCHER` names the launcher explicitly, otherwise
+/// `RUSTC_WRAPPER` is reused if it is `sccache` or `ccache`. The launcher is
+/// only applied if it can be found.
+fn configure_compiler_launcher(config: &mut CMakeConfig) {
+    let launcher = env_var("LIBOPUS_COMPILER_LAUNCHER").or_else(|| {
+        env_var("RUSTC_WRAPPER").filter(|wrapper| {
+            let wrapper_name = Path::new(wrapper)
+                .file_stem()
+                .and_then(|name| name.to_str());
+
+            matches!(wrapper_name, Some("sccache" | "ccache"))
+        })
+    });
+
+    let Some(launcher) = launcher.filter(|launcher| !launcher.is_empty()) else {
+        return;
+    };
+
+    match find_in_path(&launcher) {
+        Some(launcher_path) => {
+            info!("Using compiler launcher `{}`.", launcher_path.display());
+            config.define("CMAKE_C_COMPILER_LAUNCHER", launcher_path);
         }
+        None => println!(
+            "cargo:warning=Could not find the compiler launcher `{}`, building Opus without it.",
+            launcher
+        ),
     }
+}
 
-    if let Some(installed_opus) = find_installed_opus() {
-        link_opus(is_static, installed_opus);
+/// Passes the CMake toolchain file set via `LIBOPUS_CMAKE_TOOLCHAIN` or
+/// `OPUS_CMAKE_TOOLCHAIN` to CMake, falling back to the Android NDK's
+/// toolchain file when targeting Android.
+///
+/// Likewise, Emscripten targets use Emscripten's toolchain file. Other
+/// WebAssembly targets, such as `wasm32-wasip1`, are built without it.
+///
+/// Returns whether a toolchain file is used.
+fn configure_toolchain_file(config: &mut CMakeConfig) -> Result<bool, BuildError> {
+    let Some(toolchain_file) = opus_env_var("CMAKE_TOOLCHAIN") else {
+        if target_cfg("target_os") == "android" {
+            configure_android_ndk(config)?;
+
+            return Ok(true);
+        }
+
+        if target_cfg("target_os") == "emscripten" {
+            configure_emscripten(config)?;
+
+            return Ok(true);
+        }
+
+        return Ok(false);
+    };
+
+    let toolchain_file = PathBuf::from(toolchain_file);
+
+    if !toolchain_file.is_file() {
+        return Err(BuildError::MissingToolchainFile(toolchain_file));
+    }
+
+    println!("cargo:rerun-if-changed={}", toolchain_file.display());
+    info!("Using CMake toolchain file `{}`.", toolchain_file.display());
+    config.define("CMAKE_TOOLCHAIN_FILE", toolchain_file);
+
+    Ok(true)
+}
+
+/// Configures CMake to cross-compile via the Android NDK located at
+/// `ANDROID_NDK_HOME` or `ANDROID_NDK_ROOT`.
+///
+/// The minimum API level is taken from `ANDROID_PLATFORM`, e.g. `android-24`
+/// or `24`, and defaults to 21.
+fn configure_android_ndk(config: &mut CMakeConfig) -> Result<(), BuildError> {
+    let target = env::var("TARGET").unwrap_or_default();
+    let Some(android_abi) = target_settings::android_abi(&target) else {
+        return Err(BuildError::UnsupportedAndroidTarget(target));
+    };
+
+    let android_ndk = env_var("ANDROID_NDK_HOME")
+        .or_else(|| env_var("ANDROID_NDK_ROOT"))
+        .ok_or(BuildError::MissingAndroidNdk)?;
+
+    let toolchain_file = target_settings::android_toolchain_file(Path::new(&android_ndk));
+
+    if !toolchain_file.is_file() {
+        return Err(BuildError::MissingToolchainFile(toolchain_file));
+    }
+
+    let android_platform =
+        target_settings::android_platform(env_var("ANDROID_PLATFORM").as_deref());
+
+    info!(
+        "Building for Android ABI {} and platform {} via NDK `{}`.",
+        android_abi, android_platform, android_ndk
+    );
+
+    config.define("CMAKE_TOOLCHAIN_FILE", toolchain_file);
+    config.define("ANDROID_ABI", android_abi);
+    config.define("ANDROID_PLATFORM", android_platform);
+
+    Ok(())
+}
+
+/// Configures CMake to build via Emscripten, which is located via `EMSDK`.
+fn configure_emscripten(config: &mut CMakeConfig) -> Result<(), BuildError> {
+    let emsdk = env_var("EMSDK").ok_or(BuildError::MissingEmsdk)?;
+
+    let toolchain_file = Path::new(&emsdk)
+        .join("upstream")
+        .join("emscripten")
+        .join("cmake")
+        .join("Modules")
+        .join("Platform")
+        .join("Emscripten.cmake");
+
+    if !toolchain_file.is_file() {
+        return Err(BuildError::MissingToolchainFile(toolchain_file));
+    }
+
+    info!("Building for WebAssembly via Emscripten `{}`.", emsdk);
+
+    config.define("CMAKE_TOOLCHAIN_FILE", toolchain_file);
+    // SSE and NEON intrinsics do not exist in WebAssembly.
+    config.define("OPUS_DISABLE_INTRINSICS", "ON");
+
+    Ok(())
+}
+
+/// Returns whether the build must not access the network, i.e. neither
+/// clone Opus nor download anything, as set via `LIBOPUS_OFFLINE` or Cargo's
+/// `--offline`.
+fn is_offline() -> bool {
+    env_flag("LIBOPUS_OFFLINE").unwrap_or(false)
+        || env_var("CARGO_NET_OFFLINE").is_some_and(|offline| offline == "true")
+}
+
+/// Makes sure the Opus source at `opus_path` is present, returning the path
+/// to build Opus from.
+///
+/// If the Git submodule is not initialised and `LIBOPUS_INIT_SUBMODULE` is
+/// set, the submodule is initialised. Otherwise, if `LIBOPUS_ALLOW_CLONE` is
+/// set, the pinned Opus release is cloned into `OUT_DIR`.
+///
+/// If `requested_version` is set and the source at `opus_path` is of another
+/// version, that release is cloned instead, if allowed.
+fn ensure_opus_source(
+    opus_path: &Path,
+    requested_version: Option<&'static str>,
+) -> Result<PathBuf, BuildError> {
+    let cmake_lists = opus_path.join("CMakeLists.txt");
+
+    if cmake_lists.is_file() {
+        let Some(requested_version) = requested_version else {
+            return Ok(opus_path.to_path_buf());
+        };
+
+        let source_version = detect_source_version(opus_path);
+
+        if source_version.as_deref() == Some(requested_version) {
+            return Ok(opus_path.to_path_buf());
+        }
+
+        if is_offline() || env_var("LIBOPUS_ALLOW_CLONE").is_none() {
+            return Err(BuildError::OpusVersionMismatch(
+                requested_version,
+                source_version,
+            ));
+        }
+
+        return clone_opus(requested_version);
+    }
+
+    let is_offline = is_offline();
+
+    if Path::new(".git").exists() && env_var("LIBOPUS_INIT_SUBMODULE").is_some() {
+        if is_offline {
+            return Err(BuildError::OfflineWithoutOpusSource(
+                opus_path.to_path_buf(),
+            ));
+        }
+
+        println!("cargo:warning=Initialising the Opus submodule.");
+
+        let status = Command::new("git")
+            .args(["submodule", "update", "--init", "--recursive", "--"])
+            .arg(opus_path)
+            .status()
+            .map_err(BuildError::InitSubmodule)?;
+
+        if !status.success() {
+            return Err(BuildError::InitSubmodule(io::Error::other(format!(
+                "`git submodule update` exited with {}",
+                status
+            ))));
+        }
+
+        if cmake_lists.is_file() {
+            return ensure_opus_source(opus_path, requested_version);
+        }
+    }
+
+    if is_offline {
+        return Err(BuildError::OfflineWithoutOpusSource(
+            opus_path.to_path_buf(),
+        ));
+    }
+
+    if env_var("LIBOPUS_ALLOW_CLONE").is_some() {
+        let clone_version = requested_version.unwrap_or(OPUS_CLONE_TAG.trim_start_matches('v'));
+
+        return clone_opus(clone_version);
+    }
+
+    Err(BuildError::MissingOpusSource(opus_path.to_path_buf()))
+}
+
+/// Returns the Opus release set via `LIBOPUS_VERSION`, e.g. `1.5.2`, after
+/// checking it is among the `OPUS_RELEASES`.
+fn requested_opus_version() -> Result<Option<&'static str>, BuildError> {
+    let Some(version) = env_var("LIBOPUS_VERSION") else {
+        return Ok(None);
+    };
+
+    let version = version.trim().trim_start_matches('v');
+
+    OPUS_RELEASES
+        .iter()
+        .find(|(release, _)| *release == version)
+        .map(|(release, _)| Some(*release))
+        .ok_or_else(|| BuildError::UnsupportedOpusVersion(version.to_string()))
+}
+
+/// Detects the version of the Opus source at `opus_path`, via the
+/// `package_version` of release archives or the Git tag of the checkout.
+fn detect_source_version(opus_path: &Path) -> Option<String> {
+    // Release archives contain e.g. `PACKAGE_VERSION="1.5.2"`.
+    if let Ok(package_version) = fs::read_to_string(opus_path.join("package_version")) {
+        return package_version
+            .trim()
+            .strip_prefix("PACKAGE_VERSION=")
+            .map(|version| version.trim_matches('"').to_string());
+    }
+
+    run_git(&[
+        "-C".as_ref(),
+        opus_path.as_os_str(),
+        "describe".as_ref(),
+        "--tags".as_ref(),
+        "--exact-match".as_ref(),
+    ])
+    .ok()
+    .map(|tag| tag.trim_start_matches('v').to_string())
+}
+
+/// Runs `git` with `arguments`, returning its standard output.
+fn run_git(arguments: &[&OsStr]) -> io::Result<String> {
+    let output = Command::new("git").args(arguments).output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`git` exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Makes sure the Opus source contains the neural network model data that
+/// DRED and deep PLC require, if the `dred`-feature is enabled.
+///
+/// Git checkouts of Opus lack the data. If `LIBOPUS_DOWNLOAD_MODEL` is set,
+/// the source is copied into `OUT_DIR` and the data downloaded there via the
+/// script Opus provides. Returns the path of the source to build.
+fn ensure_dnn_model(opus_path: &Path) -> Result<PathBuf, BuildError> {
+    let has_model = |opus_path: &Path| opus_path.join("dnn").join("pitchdnn_data.c").is_file();
+
+    if !cfg!(feature = "dred") || has_model(opus_path) {
+        return Ok(opus_path.to_path_buf());
+    }
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("Cargo did not set `OUT_DIR`"));
+    let model_opus_path = out_dir.join("opus-dnn");
+
+    if has_model(&model_opus_path) {
+        return Ok(model_opus_path);
+    }
+
+    if env_var("LIBOPUS_DOWNLOAD_MODEL").is_none() {
+        return Err(BuildError::MissingDnnModel);
+    }
+
+    if is_offline() {
+        println!("cargo:warning=Not downloading the Opus model data while offline.");
+
+        return Err(BuildError::MissingDnnModel);
+    }
+
+    // `autogen.sh` names the model matching the source, e.g. via
+    // `dnn/download_model.sh "735117b"`.
+    let autogen =
+        fs::read_to_string(opus_path.join("autogen.sh")).map_err(BuildError::DownloadDnnModel)?;
+    let model_hash = autogen
+        .lines()
+        .filter_map(|line| line.split_once("download_model.sh"))
+        .filter_map(|(_, arguments)| arguments.split_whitespace().next())
+        .map(|model_hash| model_hash.trim_matches('"'))
+        .next()
+        .ok_or_else(|| {
+            BuildError::DownloadDnnModel(io::Error::other("`autogen.sh` does not name a model"))
+        })?;
+
+    decision!("Downloading the Opus model data {}.", model_hash);
+
+    // A previous, interrupted download is started over.
+    if model_opus_path.exists() {
+        fs::remove_dir_all(&model_opus_path).map_err(BuildError::DownloadDnnModel)?;
+    }
+
+    copy_dir(opus_path, &model_opus_path, &[".git"]).map_err(BuildError::DownloadDnnModel)?;
+
+    let mut download = if cfg!(windows) {
+        let mut download = Command::new("cmd");
+        download.args(["/C", "dnn\\download_model.bat"]);
+        download
+    } else {
+        let mut download = Command::new("sh");
+        download.arg("dnn/download_model.sh");
+        download
+    };
+
+    let status = download
+        .arg(model_hash)
+        .current_dir(&model_opus_path)
+        .status()
+        .map_err(BuildError::DownloadDnnModel)?;
+
+    if !status.success() || !has_model(&model_opus_path) {
+        return Err(BuildError::DownloadDnnModel(io::Error::other(format!(
+            "the download script exited with {}",
+            status
+        ))));
+    }
+
+    Ok(model_opus_path)
+}
+
+/// Shallow-clones the Opus release `version` into `OUT_DIR`, unless already
+/// cloned.
+///
+/// The clone is verified to be checked out at the release's commit listed in
+/// `OPUS_RELEASES`.
+fn clone_opus(version: &str) -> Result<PathBuf, BuildError> {
+    let Some((_, expected_commit)) = OPUS_RELEASES
+        .iter()
+        .find(|(release, _)| *release == version)
+    else {
+        return Err(BuildError::UnsupportedOpusVersion(version.to_string()));
+    };
+
+    let tag = format!("v{}", version);
+    let clone_error = |why| BuildError::CloneOpus(tag.clone(), why);
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("Cargo did not set `OUT_DIR`"));
+    let clone_dir = out_dir.join(format!("opus-{}", tag));
+
+    if !clone_dir.join("CMakeLists.txt").is_file() {
+        decision!("Cloning Opus {} from {}.", tag, OPUS_CLONE_URL);
+
+        // A previous, interrupted clone is started over.
+        if clone_dir.exists() {
+            fs::remove_dir_all(&clone_dir).map_err(clone_error)?;
+        }
+
+        run_git(&[
+            "clone".as_ref(),
+            "--depth=1".as_ref(),
+            "--branch".as_ref(),
+            tag.as_ref(),
+            OPUS_CLONE_URL.as_ref(),
+            clone_dir.as_os_str(),
+        ])
+        .map_err(clone_error)?;
+    }
+
+    let commit = run_git(&[
+        "-C".as_ref(),
+        clone_dir.as_os_str(),
+        "rev-parse".as_ref(),
+        "HEAD".as_ref(),
+    ])
+    .map_err(clone_error)?;
+
+    if commit != *expected_commit {
+        let _ = fs::remove_dir_all(&clone_dir);
+
+        return Err(clone_error(io::Error::other(format!(
+            "expected commit {}, but found {}",
+            expected_commit, commit
+        ))));
+    }
+
+    Ok(clone_dir)
+}
+
+/// Keeps timestamps and absolute paths out of the Opus build, so that the same
+/// source produces the same library on every machine.
+///
+/// `SOURCE_DATE_EPOCH` is passed on if set, otherwise it is set to `0`. Paths
+/// below `opus_path` and `OUT_DIR` are remapped to relative ones.
+fn configure_reproducible(config: &mut CMakeConfig, opus_path: &Path) {
+    info!("Building Opus reproducibly.");
+
+    let source_date_epoch = env_var("SOURCE_DATE_EPOCH").unwrap_or_else(|| String::from("0"));
+    config.env("SOURCE_DATE_EPOCH", source_date_epoch);
+
+    if target_cfg("target_env") == "msvc" {
+        // MSVC's equivalent of remapping paths and omitting timestamps.
+        config.cflag("/Brepro");
+
+        return;
+    }
+
+    config.cflag(format!("-ffile-prefix-map={}=opus", opus_path.display()));
+
+    if let Ok(out_dir) = env::var("OUT_DIR") {
+        config.cflag(format!("-ffile-prefix-map={}=.", out_dir));
+    }
+}
+
+/// Controls Opus's CPU intrinsics via `LIBOPUS_INTRINSICS`.
+///
+/// - `off` disables all intrinsics, building for the baseline ISA.
+/// - `target` presumes exactly the x86 features enabled for the Rust target,
+///   e.g. via `-C target-cpu`, instead of Opus's own defaults. Features not
+///   presumed are still used if detected at runtime.
+///
+/// Without `LIBOPUS_INTRINSICS`, Opus picks intrinsics itself.
+fn configure_intrinsics(config: &mut CMakeConfig) {
+    /// Pairs of Rust target features and the Opus option presuming them.
+    const X86_PRESUMED_FEATURES: &[(&str, &str)] = &[
+        ("sse", "OPUS_X86_PRESUME_SSE"),
+        ("sse2", "OPUS_X86_PRESUME_SSE2"),
+        ("sse4.1", "OPUS_X86_PRESUME_SSE4_1"),
+        ("avx2", "OPUS_X86_PRESUME_AVX2"),
+    ];
+
+    let Some(intrinsics) = env_var("LIBOPUS_INTRINSICS") else {
+        return;
+    };
+
+    match intrinsics.to_ascii_lowercase().as_str() {
+        "off" => {
+            info!("Building Opus without CPU intrinsics.");
+            config.define("OPUS_DISABLE_INTRINSICS", "ON");
+        }
+        "target" => {
+            let target_arch = target_cfg("target_arch");
+
+            if target_arch != "x86" && target_arch != "x86_64" {
+                return;
+            }
+
+            for (target_feature, presume_option) in X86_PRESUMED_FEATURES {
+                let presume_value = cmake_bool(has_target_feature(target_feature));
+
+                info!("Setting {}={} for CMake.", presume_option, presume_value);
+                config.define(presume_option, presume_value);
+            }
+        }
+        _ => println!(
+            "cargo:warning=Ignoring `LIBOPUS_INTRINSICS={}`, expected `off` or `target`.",
+            intrinsics
+        ),
+    }
+}
+
+/// Enables Opus's NEON optimisations on ARM targets whose Rust target
+/// features include `neon`, which cross builds otherwise often leave to
+/// runtime detection or disable.
+///
+/// `LIBOPUS_NEON` overrides this: `presume` always assumes NEON, `runtime`
+/// only uses it if detected at runtime, and `off` disables it. Nothing is
+/// changed if `LIBOPUS_INTRINSICS=off`.
+fn configure_neon(config: &mut CMakeConfig) {
+    let target_arch = target_cfg("target_arch");
+
+    if target_arch != "arm" && target_arch != "aarch64" {
+        return;
+    }
+
+    if env_var("LIBOPUS_INTRINSICS")
+        .is_some_and(|intrinsics| intrinsics.eq_ignore_ascii_case("off"))
+    {
+        return;
+    }
+
+    let neon = env_var("LIBOPUS_NEON").map(|neon| neon.to_ascii_lowercase());
+
+    let (may_have_neon, presume_neon) = match neon.as_deref() {
+        Some("presume") => (true, true),
+        Some("runtime") => (true, false),
+        Some("off") => (false, false),
+        Some(neon) => {
+            println!(
+                "cargo:warning=Ignoring `LIBOPUS_NEON={}`, expected `presume`, `runtime`, or `off`.",
+                neon
+            );
+
+            return;
+        }
+        None if has_target_feature("neon") => (true, true),
+        None => return,
+    };
+
+    info!(
+        "Setting OPUS_MAY_HAVE_NEON={} and OPUS_PRESUME_NEON={} for CMake.",
+        cmake_bool(may_have_neon),
+        cmake_bool(presume_neon)
+    );
+    config.define("OPUS_MAY_HAVE_NEON", cmake_bool(may_have_neon));
+    config.define("OPUS_PRESUME_NEON", cmake_bool(presume_neon));
+}
+
+/// Lets the C compiler vectorise Opus on RISC-V targets whose Rust target
+/// features include the vector extension `v`.
+///
+/// Opus has no RISC-V intrinsics of its own, thus the `-march` derived from
+/// the target features is extended by `v`, so that the compiler vectorises
+/// Opus's portable C code, see [`riscv_march`](target_settings::riscv_march).
+/// Nothing is changed if `LIBOPUS_INTRINSICS=off`.
+fn configure_riscv(config: &mut CMakeConfig) {
+    if env_var("LIBOPUS_INTRINSICS")
+        .is_some_and(|intrinsics| intrinsics.eq_ignore_ascii_case("off"))
+    {
+        return;
+    }
+
+    let Some(march) = target_settings::riscv_march(&target_cfg("target_arch"), has_target_feature)
+    else {
+        return;
+    };
+
+    info!("Building Opus with `-march={}` for RISC-V vectors.", march);
+    config.cflag(format!("-march={}", march));
+}
+
+/// Returns whether Opus ends up in a shared Rust artifact, i.e. a `cdylib`
+/// or `dylib`, as set via `LIBOPUS_CDYLIB` or passed via `--crate-type` in
+/// `RUSTFLAGS`.
+///
+/// Build scripts are not told the crate types of dependent crates, thus this
+/// relies on these hints.
+fn is_shared_artifact() -> bool {
+    env_flag("LIBOPUS_CDYLIB").unwrap_or_else(|| {
+        let rustflags = env_var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default();
+        let rustflags = rustflags.split('\x1f').collect::<Vec<_>>();
+
+        rustflags.windows(2).any(|flags| {
+            flags[0] == "--crate-type" && (flags[1] == "cdylib" || flags[1] == "dylib")
+        }) || rustflags
+            .iter()
+            .any(|flag| *flag == "--crate-type=cdylib" || *flag == "--crate-type=dylib")
+    })
+}
+
+/// Returns whether the target needs position-independent code to link static
+/// libraries into shared ones, unlike Windows and Apple targets, which build
+/// position-independent code anyway.
+fn needs_position_independent_code() -> bool {
+    let is_unix = target_cfg("target_family")
+        .split(',')
+        .any(|family| family == "unix");
+
+    is_unix && target_cfg("target_vendor") != "apple"
+}
+
+/// Builds a static Opus as position-independent code on Linux, or on other
+/// Unix-like targets if it ends up in a `cdylib`, see [`is_shared_artifact`],
+/// e.g. a Python or Node module.
+///
+/// `LIBOPUS_PIC` overrides this, e.g. to leave it off for static executables.
+/// Leaving it off for a `cdylib` is warned about, as linking then fails with
+/// relocation errors.
+fn configure_position_independent(config: &mut CMakeConfig, is_static: bool) {
+    // A shared library is always built as position-independent code.
+    if !is_static {
+        return;
+    }
+
+    let is_shared_artifact = is_shared_artifact();
+    let is_position_independent = env_flag("LIBOPUS_PIC")
+        .unwrap_or_else(|| target_cfg("target_os") == "linux" || is_shared_artifact);
+
+    if is_position_independent {
+        info!("Building Opus as position-independent code.");
+    } else if is_shared_artifact && needs_position_independent_code() {
+        println!(
+            "cargo:warning=Building a static Opus for a `cdylib` without position-independent \
+             code, linking will likely fail with relocation errors. Set `LIBOPUS_PIC=ON`."
+        );
+    }
+
+    config.define(
+        "CMAKE_POSITION_INDEPENDENT_CODE",
+        cmake_bool(is_position_independent),
+    );
+}
+
+/// Builds a static Opus with link-time optimisation, if the `lto`-feature is
+/// enabled or `LIBOPUS_LTO` is set, and the C compiler is GCC or MSVC.
+///
+/// GCC emits fat objects, which carry machine code next to the LTO bytecode,
+/// so that any linker can still link Opus. MSVC's `/GL` objects are linked by
+/// `link.exe`, which Rust uses as well. Clang's bitcode would require a linker
+/// matching Clang's LLVM, thus Opus is built without LTO for Clang.
+fn configure_lto(config: &mut CMakeConfig, is_static: bool) {
+    if !env_flag("LIBOPUS_LTO").unwrap_or(cfg!(feature = "lto")) {
+        return;
+    }
+
+    if !is_static {
+        println!("cargo:warning=LTO only applies to a static Opus, building it without.");
+
+        return;
+    }
+
+    let mut build = cc::Build::new();
+    build.cargo_metadata(false).warnings(false);
+
+    let compiler = build.get_compiler();
+
+    if compiler.is_like_msvc() && !compiler.is_like_clang_cl() {
+        info!("Building Opus with LTO via `/GL`.");
+
+        // `CMAKE_INTERPROCEDURAL_OPTIMIZATION` is only respected with policy CMP0069.
+        config.define("CMAKE_POLICY_DEFAULT_CMP0069", "NEW");
+        config.define("CMAKE_INTERPROCEDURAL_OPTIMIZATION", "ON");
+    } else if compiler.is_like_gnu() && !compiler.is_like_clang() {
+        if !build
+            .is_flag_supported("-ffat-lto-objects")
+            .unwrap_or(false)
+        {
+            println!(
+                "cargo:warning=GCC does not support `-ffat-lto-objects`, building Opus without \
+                 LTO."
+            );
+
+            return;
+        }
+
+        info!("Building Opus with LTO via `-flto -ffat-lto-objects`.");
+
+        // CMake's own LTO for GCC passes `-fno-fat-lto-objects`, thus the flags
+        // are passed directly.
+        config.cflag("-flto");
+        config.cflag("-ffat-lto-objects");
+    } else {
+        println!(
+            "cargo:warning=LTO is only supported for Opus built by GCC or MSVC, building Opus \
+             without LTO."
+        );
+    }
+}
+
+/// Builds a universal macOS Opus for the architectures set via
+/// `LIBOPUS_MACOS_ARCHS`, e.g. `arm64;x86_64`.
+///
+/// Returns the architectures, if any were set and the target is macOS.
+fn configure_macos_architectures(config: &mut CMakeConfig) -> Option<Vec<String>> {
+    if target_cfg("target_os") != "macos" {
+        return None;
+    }
+
+    let macos_architectures = env_var("LIBOPUS_MACOS_ARCHS")?
+        .split([';', ','])
+        .map(str::trim)
+        .filter(|architecture| !architecture.is_empty())
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    // Apple calls `aarch64` `arm64`.
+    let target_architecture = match target_cfg("target_arch").as_str() {
+        "aarch64" => String::from("arm64"),
+        target_architecture => target_architecture.to_string(),
+    };
+
+    if !macos_architectures.contains(&target_architecture) {
+        println!(
+            "cargo:warning=`LIBOPUS_MACOS_ARCHS` lacks the target's architecture {}, \
+             linking Opus will fail.",
+            target_architecture
+        );
+    }
+
+    let cmake_architectures = macos_architectures.join(";");
+
+    info!(
+        "Building Opus for macOS architectures {}.",
+        cmake_architectures
+    );
+    config.define("CMAKE_OSX_ARCHITECTURES", cmake_architectures);
+
+    Some(macos_architectures)
+}
+
+/// Builds Opus for the macOS version set via `MACOSX_DEPLOYMENT_TARGET`, like
+/// `rustc`, so that Opus and Rust agree on the deployment target.
+///
+/// If unset or empty, `rustc`'s default minimum for the architecture is used.
+fn configure_macos_deployment_target(config: &mut CMakeConfig) {
+    if target_cfg("target_os") != "macos" {
+        return;
+    }
+
+    let deployment_target = env_var("MACOSX_DEPLOYMENT_TARGET")
+        .map(|deployment_target| deployment_target.trim().to_string())
+        .filter(|deployment_target| !deployment_target.is_empty())
+        .unwrap_or_else(|| {
+            if target_cfg("target_arch") == "aarch64" {
+                String::from("11.0")
+            } else {
+                String::from("10.12")
+            }
+        });
+
+    info!("Using macOS deployment target {}.", deployment_target);
+    config.define("CMAKE_OSX_DEPLOYMENT_TARGET", deployment_target);
+}
+
+/// Warns if the static Opus in `opus_build_dir` misses any of the
+/// `macos_architectures`, as reported by `lipo`.
+fn verify_macos_architectures(opus_build_dir: &Path, macos_architectures: &[String]) {
+    let static_library = opus_build_dir.join("lib").join("libopus.a");
+
+    let Ok(output) = Command::new("lipo")
+        .arg("-archs")
+        .arg(&static_library)
+        .output()
+    else {
+        return;
+    };
+
+    let built_architectures = String::from_utf8_lossy(&output.stdout);
+
+    for architecture in macos_architectures {
+        if !built_architectures
+            .split_whitespace()
+            .any(|built_architecture| built_architecture == architecture)
+        {
+            println!(
+                "cargo:warning=`{}` does not contain the architecture {}.",
+                static_library.display(),
+                architecture
+            );
+        }
+    }
+}
+
+/// The build system compiling the bundled Opus.
+enum BuildSystem {
+    /// Opus's own CMake project, the default.
+    CMake,
+    /// The `cc` crate compiling Opus's C sources directly.
+    Cc,
+    /// Opus's Meson project, built via Ninja.
+    Meson,
+}
+
+/// Picks the build system set via `LIBOPUS_BUILD_SYSTEM`, otherwise `cc` if
+/// the `cc-build`-feature is enabled or CMake cannot be run, and CMake
+/// otherwise.
+///
+/// `cc` only builds a static Opus, thus a dynamic Opus is always built via
+/// CMake unless Meson is picked.
+fn select_build_system(is_static: bool) -> BuildSystem {
+    if let Some(build_system) = env_var("LIBOPUS_BUILD_SYSTEM") {
+        match build_system.to_ascii_lowercase().as_str() {
+            "cmake" => return BuildSystem::CMake,
+            "meson" => return BuildSystem::Meson,
+            "cc" if is_static => return BuildSystem::Cc,
+            "cc" => println!(
+                "cargo:warning=`LIBOPUS_BUILD_SYSTEM=cc` only builds a static Opus, \
+                 building the dynamic Opus via CMake."
+            ),
+            _ => println!(
+                "cargo:warning=Ignoring `LIBOPUS_BUILD_SYSTEM={}`, expected `cmake`, `meson`, \
+                 or `cc`.",
+                build_system
+            ),
+        }
+    }
+
+    if cfg!(feature = "cc-build") {
+        if is_static {
+            return BuildSystem::Cc;
+        }
+
+        println!(
+            "cargo:warning=The `cc-build`-feature only builds a static Opus, \
+             building the dynamic Opus via CMake."
+        );
+    } else if is_static && detect_cmake_version().is_err() {
+        println!("cargo:warning=Could not run `cmake`, building Opus via `cc` instead.");
+
+        return BuildSystem::Cc;
+    }
+
+    BuildSystem::CMake
+}
+
+/// Reads the source files assigned to any of `variables` in `makefile`, one
+/// of the `*_sources.mk` files listing Opus's sources.
+fn read_opus_sources(
+    opus_path: &Path,
+    makefile: &str,
+    variables: &[&str],
+) -> Result<Vec<PathBuf>, BuildError> {
+    let makefile = opus_path.join(makefile);
+    let contents = fs::read_to_string(&makefile)
+        .map_err(|why| BuildError::ReadOpusSources(makefile.clone(), why))?;
+
+    // Joins the lines continued via a trailing backslash.
+    let contents = contents.replace("\\\r\n", " ").replace("\\\n", " ");
+
+    let sources = contents
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter(|(variable, _)| variables.contains(&variable.trim()))
+        .flat_map(|(_, files)| files.split_whitespace())
+        .map(|file| opus_path.join(file))
+        .collect();
+
+    Ok(sources)
+}
+
+/// Builds a static Opus via the `cc` crate, without requiring CMake, and
+/// returns the directory it was placed in.
+///
+/// Opus is built for the baseline instruction set, its SSE, AVX, and NEON
+/// sources are left out.
+fn build_opus_with_cc(opus_path: &Path) -> Result<PathBuf, BuildError> {
+    decision!("Building Opus via `cc`.");
+
+    let is_fixed_point = cfg!(feature = "fixed-point");
+    let silk_variant = if is_fixed_point { "fixed" } else { "float" };
+
+    let has_float_api = !cfg!(feature = "no-float-api");
+
+    let mut sources = read_opus_sources(
+        opus_path,
+        "opus_sources.mk",
+        if has_float_api {
+            &["OPUS_SOURCES", "OPUS_SOURCES_FLOAT"]
+        } else {
+            &["OPUS_SOURCES"]
+        },
+    )?;
+    sources.extend(read_opus_sources(
+        opus_path,
+        "celt_sources.mk",
+        &["CELT_SOURCES"],
+    )?);
+    sources.extend(read_opus_sources(
+        opus_path,
+        "silk_sources.mk",
+        &[
+            "SILK_SOURCES",
+            if is_fixed_point {
+                "SILK_SOURCES_FIXED"
+            } else {
+                "SILK_SOURCES_FLOAT"
+            },
+        ],
+    )?);
+
+    info!("Compiling {} Opus sources.", sources.len());
+
+    let mut build = cc::Build::new();
+    build
+        .files(&sources)
+        .include(opus_path.join("include"))
+        .include(opus_path.join("celt"))
+        .include(opus_path.join("silk"))
+        .include(opus_path.join("silk").join(silk_variant))
+        .include(opus_path)
+        .define("OPUS_BUILD", None)
+        .define("HAVE_LRINTF", None)
+        .define("HAVE_LRINT", None)
+        .warnings(false)
+        .cargo_metadata(false);
+
+    // Only Opus's own build systems define `PACKAGE_VERSION`, which like them
+    // is `unknown` unless the source is a release or an exact tag.
+    let version = detect_source_version(opus_path).unwrap_or_else(|| String::from("unknown"));
+    build.define("PACKAGE_VERSION", format!("\"{}\"", version).as_str());
+
+    // Since Opus 1.5, the sources include the headers of its neural network
+    // features, even when these are disabled.
+    let dnn_dir = opus_path.join("dnn");
+
+    if dnn_dir.is_dir() {
+        build.include(dnn_dir);
+    }
+
+    // MSVC lacks variable-length arrays.
+    if target_cfg("target_env") == "msvc" {
+        build.define("USE_ALLOCA", None);
+    } else {
+        build.define("VAR_ARRAYS", None);
+    }
+
+    if is_fixed_point {
+        info!("Building Opus with fixed-point arithmetic.");
+        build.define("FIXED_POINT", None);
+    }
+
+    if cfg!(feature = "custom") {
+        info!("Building Opus with custom modes.");
+        build.define("CUSTOM_MODES", None);
+    }
+
+    if !has_float_api {
+        info!("Building Opus without its float API.");
+        build.define("DISABLE_FLOAT_API", None);
+    }
+
+    if cfg!(feature = "dred") {
+        println!(
+            "cargo:warning=Building Opus via `cc` does not support the `dred`-feature, \
+             building it without DRED and deep PLC."
+        );
+    }
+
+    // Mirrors the defaults of Opus's CMake project.
+    if env_flag("LIBOPUS_ASSERTIONS").unwrap_or(false) {
+        build.define("ENABLE_ASSERTIONS", None);
+    }
+
+    if env_flag("LIBOPUS_HARDENING").unwrap_or(true) {
+        build.define("ENABLE_HARDENING", None);
+    }
+
+    if let Some(is_position_independent) = env_flag("LIBOPUS_PIC") {
+        build.pic(is_position_independent);
+    }
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("Cargo did not set `OUT_DIR`"));
+    let opus_build_dir = out_dir.join("opus-cc");
+    let include_dir = opus_build_dir.join("include").join("opus");
+
+    copy_dir(&opus_path.join("include"), &include_dir, &[]).map_err(BuildError::CopyOpusHeaders)?;
+
+    build
+        .out_dir(opus_build_dir.join("lib"))
+        .try_compile("opus")
+        .map_err(BuildError::CompileOpus)?;
+
+    Ok(opus_build_dir)
+}
+
+/// Runs `meson` with `arguments`, failing with its output if it exits
+/// unsuccessfully.
+fn run_meson(arguments: &[&OsStr]) -> io::Result<()> {
+    let output = Command::new("meson").args(arguments).output()?;
+
+    if !output.status.success() {
+        // Meson reports most errors on stdout.
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let report = if stderr.trim().is_empty() {
+            stdout
+        } else {
+            stderr
+        };
+
+        return Err(io::Error::other(format!(
+            "`meson` exited with {}: {}",
+            output.status,
+            report.trim()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Maps a CMake build type to the matching Meson one.
+fn meson_build_type(build_type: &str) -> &'static str {
+    match build_type {
+        "Debug" => "debug",
+        "RelWithDebInfo" => "debugoptimized",
+        "MinSizeRel" => "minsize",
+        _ => "release",
+    }
+}
+
+/// Returns the Meson cross file set via `LIBOPUS_MESON_CROSS_FILE`.
+///
+/// Unlike the `cmake` crate, Meson is not told the target otherwise, thus
+/// cross-compiling without a cross file fails instead of building for the
+/// host.
+fn meson_cross_file() -> Result<Option<PathBuf>, BuildError> {
+    let target = env::var("TARGET").unwrap_or_default();
+    let is_cross_compiling = env::var("HOST").is_ok_and(|host| host != target);
+
+    let Some(cross_file) = env_var("LIBOPUS_MESON_CROSS_FILE") else {
+        if is_cross_compiling {
+            return Err(BuildError::MesonCrossFileRequired(target));
+        }
+
+        return Ok(None);
+    };
+
+    let cross_file = PathBuf::from(cross_file);
+
+    if !cross_file.is_file() {
+        return Err(BuildError::MissingMesonCrossFile(cross_file));
+    }
+
+    println!("cargo:rerun-if-changed={}", cross_file.display());
+    info!("Using Meson cross file `{}`.", cross_file.display());
+
+    Ok(Some(cross_file))
+}
+
+/// Builds Opus via Meson and Ninja and returns the directory it was installed
+/// to.
+fn build_opus_with_meson(
+    opus_path: &Path,
+    build_type: &str,
+    is_static: bool,
+) -> Result<PathBuf, BuildError> {
+    let meson_version = Command::new("meson")
+        .arg("--version")
+        .output()
+        .map_err(|why| BuildError::MesonUnavailable(format!("could not run `meson` ({})", why)))?;
+
+    info!(
+        "Found Meson version {}.",
+        String::from_utf8_lossy(&meson_version.stdout).trim()
+    );
+
+    Command::new("ninja")
+        .arg("--version")
+        .output()
+        .map_err(|why| BuildError::MesonUnavailable(format!("could not run `ninja` ({})", why)))?;
+
+    decision!("Building Opus via Meson.");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("Cargo did not set `OUT_DIR`"));
+    let meson_build_dir = out_dir.join("meson-build");
+    let opus_build_dir = out_dir.join("opus-meson");
+
+    let build_type = meson_build_type(build_type);
+    info!("Using Meson build type {}.", build_type);
+
+    let mut options = vec![
+        format!("--prefix={}", opus_build_dir.display()),
+        String::from("--libdir=lib"),
+        format!("--buildtype={}", build_type),
+        format!(
+            "--default-library={}",
+            if is_static { "static" } else { "shared" }
+        ),
+        String::from("-Dtests=disabled"),
+        String::from("-Ddocs=disabled"),
+        String::from("-Dextra-programs=disabled"),
+        format!("-Dfixed-point={}", cfg!(feature = "fixed-point")),
+        format!("-Dcustom-modes={}", cfg!(feature = "custom")),
+        format!("-Dfloat-api={}", !cfg!(feature = "no-float-api")),
+    ];
+
+    if cfg!(feature = "dred") {
+        options.push(String::from("-Ddred=enabled"));
+        options.push(String::from("-Ddeep-plc=enabled"));
+    }
+
+    if let Some(assertions) = env_flag("LIBOPUS_ASSERTIONS") {
+        options.push(format!("-Dassertions={}", assertions));
+    }
+
+    if let Some(hardening) = env_flag("LIBOPUS_HARDENING") {
+        options.push(format!("-Dhardening={}", hardening));
+    }
+
+    if let Some(cross_file) = meson_cross_file()? {
+        options.push(format!("--cross-file={}", cross_file.display()));
+    }
+
+    // Meson could download dependencies via its wrap files.
+    if is_offline() {
+        options.push(String::from("--wrap-mode=nodownload"));
+    }
+
+    // A build directory left over from an earlier run has to be reconfigured.
+    if meson_build_dir.join("build.ninja").is_file() {
+        options.push(String::from("--reconfigure"));
+    }
+
+    let mut setup_arguments = vec![
+        OsStr::new("setup"),
+        meson_build_dir.as_os_str(),
+        opus_path.as_os_str(),
+    ];
+    setup_arguments.extend(options.iter().map(OsStr::new));
+
+    run_meson(&setup_arguments).map_err(BuildError::BuildViaMeson)?;
+
+    let jobs = env::var("NUM_JOBS").ok();
+    let mut compile_arguments = vec![OsStr::new("compile"), OsStr::new("-C")];
+    compile_arguments.push(meson_build_dir.as_os_str());
+
+    if let Some(jobs) = &jobs {
+        compile_arguments.push(OsStr::new("-j"));
+        compile_arguments.push(OsStr::new(jobs));
+    }
+
+    run_meson(&compile_arguments).map_err(BuildError::BuildViaMeson)?;
+
+    run_meson(&[
+        OsStr::new("install"),
+        OsStr::new("-C"),
+        meson_build_dir.as_os_str(),
+    ])
+    .map_err(BuildError::BuildViaMeson)?;
+
+    Ok(opus_build_dir)
+}
+
+/// Strips debug info and local symbols from the static Opus library below
+/// `opus_build_dir`, if it was built as `Release` or `MinSizeRel`.
+///
+/// Global symbols are kept, Rust links against them. The `strip` binary is
+/// taken from `STRIP_<target>`, `TARGET_STRIP`, or `STRIP`. MSVC libraries
+/// are left as is, they carry debug info in separate files.
+fn strip_opus(opus_build_dir: &Path, build_type: &str) {
+    if !matches!(build_type, "Release" | "MinSizeRel") {
+        info!("Not stripping Opus built as {}.", build_type);
+
+        return;
+    }
+
+    if target_cfg("target_env") == "msvc" {
+        info!("Not stripping Opus built for MSVC.");
+
+        return;
+    }
+
+    let Some(library) = find_library_dir(opus_build_dir)
+        .map(|library_dir| library_dir.join("libopus.a"))
+        .filter(|library| library.is_file())
+    else {
+        println!("cargo:warning=Could not find the static Opus library to strip.");
+
+        return;
+    };
+
+    let strip = target_env_var("STRIP").unwrap_or_else(|| String::from("strip"));
+
+    // Apple's `strip` lacks `--strip-unneeded`, `-S -x` is its equivalent.
+    let arguments: &[&str] = if target_cfg("target_vendor") == "apple" {
+        &["-S", "-x"]
+    } else {
+        &["--strip-unneeded"]
+    };
+
+    match Command::new(&strip).args(arguments).arg(&library).status() {
+        Ok(status) if status.success() => info!("Stripped `{}`.", library.display()),
+        Ok(status) => println!(
+            "cargo:warning=`{}` exited with {}, Opus is not stripped.",
+            strip, status
+        ),
+        Err(why) => println!(
+            "cargo:warning=Could not run `{}` ({}), Opus is not stripped.",
+            strip, why
+        ),
+    }
+}
+
+/// Configures building Opus via CMake, returning the configuration and the
+/// macOS architectures to verify the built library against, if any.
+fn configure_opus_cmake(
+    opus_path: &Path,
+    canonical_opus_path: &Path,
+    build_type: &str,
+    is_static: bool,
+    is_reproducible: bool,
+) -> Result<(CMakeConfig, Option<Vec<String>>), BuildError> {
+    let cmake_version = detect_cmake_version()?;
+    info!("Found CMake version {}.", cmake_version);
+
+    decision!("Building Opus via CMake.");
+    let mut config = CMakeConfig::new(opus_path);
+
+    info!("Using CMake build type {}.", build_type);
+    config.profile(build_type);
+
+    // Some distributions make `GNUInstallDirs` default to `lib64`.
+    config.define("CMAKE_INSTALL_LIBDIR", "lib");
+
+    // Only the library is needed, not Opus's tests, demo, and programs.
+    // `LIBOPUS_CMAKE_DEFINES` may still enable them.
+    config.define("OPUS_BUILD_TESTING", "OFF");
+    config.define("OPUS_BUILD_PROGRAMS", "OFF");
+    config.define("BUILD_TESTING", "OFF");
+
+    // The built library must match the linking, e.g. a DLL with an import
+    // library such as `opus.lib` or `libopus.dll.a` on Windows.
+    info!(
+        "Building Opus as {} library.",
+        if is_static { "static" } else { "shared" }
+    );
+    config.define("BUILD_SHARED_LIBS", cmake_bool(!is_static));
+
+    // Disable assertions and hardening to avoid debug CRT dependency on Windows
+    // Rust defaults to release CRT even in debug builds, but CMake defaults to debug CRT
+    // `LIBOPUS_ASSERTIONS` and `LIBOPUS_HARDENING` override this.
+    let is_debug = build_type == "Debug";
+    let assertions = env_flag("LIBOPUS_ASSERTIONS").or(is_debug.then_some(false));
+    let hardening = env_flag("LIBOPUS_HARDENING").or(is_debug.then_some(false));
+
+    if let Some(assertions) = assertions {
+        info!("Setting OPUS_ASSERTIONS={}.", cmake_bool(assertions));
+        config.define("OPUS_ASSERTIONS", cmake_bool(assertions));
+    }
+
+    if let Some(hardening) = hardening {
+        info!("Setting OPUS_HARDENING={}.", cmake_bool(hardening));
+        config.define("OPUS_HARDENING", cmake_bool(hardening));
+    }
+
+    if is_debug && assertions == Some(false) {
+        // Also define NO_ASSERTS to disable SILK assertions that use _ASSERTE on Windows
+        // which requires the debug CRT (__imp__CrtDbgReportW)
+        config.cflag("-DNO_ASSERTS");
+    }
+
+    if target_cfg("target_env") == "msvc" {
+        // Make Opus use the same CRT as Rust, which links the static CRT when
+        // `+crt-static` is enabled and the dynamic CRT otherwise.
+        let msvc_runtime = if has_target_feature("crt-static") {
+            "MultiThreaded"
+        } else {
+            "MultiThreadedDLL"
+        };
+
+        info!("Using MSVC runtime library {}.", msvc_runtime);
+
+        // `CMAKE_MSVC_RUNTIME_LIBRARY` is only respected with policy CMP0091.
+        config.define("CMAKE_POLICY_DEFAULT_CMP0091", "NEW");
+        config.define("CMAKE_MSVC_RUNTIME_LIBRARY", msvc_runtime);
+    }
+
+    if cfg!(feature = "custom") {
+        info!("Building Opus with custom modes.");
+        config.define("OPUS_CUSTOM_MODES", "ON");
+    }
+
+    if cfg!(feature = "fixed-point") {
+        info!("Building Opus with fixed-point arithmetic.");
+        config.define("OPUS_FIXED_POINT", "ON");
+    }
+
+    if cfg!(feature = "no-float-api") {
+        info!("Building Opus without its float API.");
+        config.define("OPUS_ENABLE_FLOAT_API", "OFF");
+    }
+
+    if cfg!(feature = "dred") {
+        info!("Building Opus with DRED and deep PLC.");
+        config.define("OPUS_DRED", "ON");
+        config.define("OPUS_DEEP_PLC", "ON");
+    }
+
+    if is_reproducible {
+        configure_reproducible(&mut config, canonical_opus_path);
+    }
+
+    let macos_architectures = configure_macos_architectures(&mut config);
+    configure_macos_deployment_target(&mut config);
+    configure_intrinsics(&mut config);
+    configure_neon(&mut config);
+    configure_riscv(&mut config);
+    configure_position_independent(&mut config, is_static);
+    configure_lto(&mut config, is_static);
+
+    configure_generator(&mut config)?;
+    configure_mingw(&mut config);
+    configure_compiler_launcher(&mut config);
+
+    // A toolchain file picks the compiler itself.
+    if !configure_toolchain_file(&mut config)? {
+        configure_compiler(&mut config);
+    }
+
+    if let Some(cmake_defines) = env_var("LIBOPUS_CMAKE_DEFINES") {
+        for (key, value) in parse_cmake_defines(&cmake_defines) {
+            info!("Defining {}={} for CMake.", key, value);
+            config.define(key, value);
+        }
+    }
+
+    Ok((config, macos_architectures))
+}
+
+/// Builds Opus via the CMake `config` and returns the directory it was
+/// installed to.
+fn build_opus_with_cmake(
+    config: &mut CMakeConfig,
+    macos_architectures: Option<&[String]>,
+    is_static: bool,
+) -> PathBuf {
+    let opus_build_dir = config.build();
+
+    if let Some(macos_architectures) = macos_architectures
+        && is_static
+    {
+        verify_macos_architectures(&opus_build_dir, macos_architectures);
+    }
+
+    opus_build_dir
+}
+
+fn build_opus(is_static: bool) -> Result<PathBuf, BuildError> {
+    let requested_version = requested_opus_version()?;
+    let opus_path = ensure_opus_source(Path::new("opus"), requested_version)?;
+
+    let opus_path = ensure_dnn_model(&opus_path)?;
+    let opus_path = opus_path.as_path();
+
+    // Canonicalising only serves absolute paths in logs and builds, thus a
+    // failure, e.g. due to a restricted symlink, must not abort the build.
+    let canonical_opus_path = opus_path.canonicalize().unwrap_or_else(|why| {
+        info!(
+            "Could not canonicalise `{}`, using it as is: {}",
+            opus_path.display(),
+            why
+        );
+
+        path::absolute(opus_path).unwrap_or_else(|_| opus_path.to_path_buf())
+    });
+
+    let is_reproducible = env_flag("LIBOPUS_REPRODUCIBLE").unwrap_or(false);
+
+    // Machine-specific paths are kept out of the build log when reproducing.
+    if is_reproducible {
+        info!("Opus source path used: {:?}.", opus_path);
+    } else {
+        info!("Opus source path used: {:?}.", canonical_opus_path);
+    }
+
+    let build_type = cmake_build_type();
+
+    let staging_prefix = env_var("LIBOPUS_STAGING_PREFIX").map(PathBuf::from);
+
+    if let Some(staging_prefix) = &staging_prefix
+        && env_var("DESTDIR").is_none()
+    {
+        return Err(BuildError::MissingDestdir(staging_prefix.clone()));
+    }
+
+    let build_start = Instant::now();
+
+    let build_system = select_build_system(is_static);
+    let is_cmake = matches!(build_system, BuildSystem::CMake);
+
+    // A cached Opus lacks the CMake build directory to stage it from.
+    let cache_dir = env_var("LIBOPUS_CACHE_DIR").filter(|_| staging_prefix.is_none());
+
+    if cache_dir.is_some() && !is_cmake {
+        println!(
+            "cargo:warning=`LIBOPUS_CACHE_DIR` only applies to Opus built via CMake, not \
+             caching it."
+        );
+    }
+
+    let mut cache_entry = None;
+
+    let opus_build_dir = match build_system {
+        BuildSystem::CMake => {
+            let (mut config, macos_architectures) = configure_opus_cmake(
+                opus_path,
+                &canonical_opus_path,
+                &build_type,
+                is_static,
+                is_reproducible,
+            )?;
+
+            // The key covers the configuration, thus it is only derived now.
+            cache_entry = cache_dir.and_then(|cache_dir| {
+                match opus_cache_key(&canonical_opus_path, &config) {
+                    Ok(cache_key) => Some(PathBuf::from(cache_dir).join(cache_key)),
+                    Err(why) => {
+                        println!(
+                            "cargo:warning=Could not derive the cache key, not caching Opus: {}",
+                            why
+                        );
+
+                        None
+                    }
+                }
+            });
+
+            if let Some(cache_entry) = &cache_entry
+                && cache_entry.is_dir()
+            {
+                decision!("Using Opus cached at `{}`.", cache_entry.display());
+
+                let include_dir = cache_entry.join("include").join("opus");
+                link_opus(is_static, cache_entry, &include_dir)?;
+
+                return Ok(cache_entry.clone());
+            }
+
+            build_opus_with_cmake(&mut config, macos_architectures.as_deref(), is_static)
+        }
+        BuildSystem::Cc => build_opus_with_cc(&canonical_opus_path)?,
+        BuildSystem::Meson => build_opus_with_meson(&canonical_opus_path, &build_type, is_static)?,
+    };
+
+    decision!(
+        "Built Opus in {:.1} seconds.",
+        build_start.elapsed().as_secs_f64()
+    );
+
+    if is_static && env_flag("LIBOPUS_STRIP").unwrap_or(false) {
+        strip_opus(&opus_build_dir, &build_type);
+    }
+
+    if let Some(cache_entry) = &cache_entry {
+        store_cached_opus(&opus_build_dir, cache_entry);
+    }
+
+    let opus_build_dir = match (staging_prefix, build_system) {
+        (Some(staging_prefix), BuildSystem::CMake) => {
+            stage_opus(&opus_build_dir, &staging_prefix, &build_type)?
+        }
+        (Some(_), _) => {
+            println!(
+                "cargo:warning=`LIBOPUS_STAGING_PREFIX` only applies to Opus built via CMake, \
+                 not staging it."
+            );
+
+            opus_build_dir
+        }
+        (None, _) => opus_build_dir,
+    };
+
+    let include_dir = opus_build_dir.join("include").join("opus");
+    link_opus(is_static, &opus_build_dir, &include_dir)?;
+
+    Ok(opus_build_dir)
+}
+
+/// Returns where `staging_prefix` is staged below `DESTDIR`, as `make install`
+/// and `cmake --install` do, or `None` if `DESTDIR` is not set.
+fn staged_path(staging_prefix: &Path) -> Option<PathBuf> {
+    let destdir = env_var("DESTDIR")?;
+
+    // The prefix is absolute, thus only its normal components are appended.
+    let staged_path = staging_prefix
+        .components()
+        .filter(|component| matches!(component, path::Component::Normal(_)))
+        .fold(PathBuf::from(destdir), |staged, component| {
+            staged.join(component)
+        });
+
+    Some(staged_path)
+}
+
+/// Installs the Opus built via CMake to `staging_prefix`, its final location,
+/// e.g. `/usr/lib/my-app`, staged below `DESTDIR` for packaging, and returns
+/// the staged prefix to link against.
+///
+/// Installed files such as `opus.pc` refer to `staging_prefix` instead of
+/// `OUT_DIR`, thus the staged tree can be moved to its final location as is.
+fn stage_opus(
+    opus_build_dir: &Path,
+    staging_prefix: &Path,
+    build_type: &str,
+) -> Result<PathBuf, BuildError> {
+    let Some(staged_prefix) = staged_path(staging_prefix) else {
+        return Err(BuildError::MissingDestdir(staging_prefix.to_path_buf()));
+    };
+
+    decision!(
+        "Installing Opus to `{}`, staged at `{}`.",
+        staging_prefix.display(),
+        staged_prefix.display()
+    );
+
+    let mut install = Command::new(cmake_binary());
+    install
+        .arg("--install")
+        .arg(opus_build_dir.join("build"))
+        .arg("--prefix")
+        .arg(staging_prefix)
+        .args(["--config", build_type]);
+
+    let status = install.status().map_err(BuildError::StageOpus)?;
+
+    if !status.success() {
+        return Err(BuildError::StageOpus(io::Error::other(format!(
+            "`cmake --install` exited with {}",
+            status
+        ))));
+    }
+
+    Ok(staged_prefix)
+}
+
+/// Hashes `value` via 64-bit FNV-1a, which, unlike the standard library's
+/// hasher, is stable across Rust versions.
+fn fnv1a_hash(hash: u64, value: &[u8]) -> u64 {
+    value.iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Calls `visit` with the path relative to the walked root, using `/` as
+/// separator, and the full path of every file below `dir` in a stable order,
+/// skipping `.git`.
+fn walk_source_files(
+    dir: &Path,
+    relative_dir: &str,
+    visit: &mut impl FnMut(&str, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut entries = dir.read_dir()?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        if entry.file_name() == ".git" {
+            continue;
+        }
+
+        let relative_path = if relative_dir.is_empty() {
+            entry.file_name().to_string_lossy().into_owned()
+        } else {
+            format!("{}/{}", relative_dir, entry.file_name().to_string_lossy())
+        };
+
+        if entry.file_type()?.is_dir() {
+            walk_source_files(&entry.path(), &relative_path, visit)?;
+        } else {
+            visit(&relative_path, &entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Hashes every file below `dir`, including their relative paths.
+fn hash_dir(hash: u64, dir: &Path) -> io::Result<u64> {
+    let mut hash = hash;
+
+    walk_source_files(dir, "", &mut |relative_path, path| {
+        hash = fnv1a_hash(hash, relative_path.as_bytes());
+        hash = fnv1a_hash(hash, &fs::read(path)?);
+
+        Ok(())
+    })?;
+
+    Ok(hash)
+}
+
+/// Derives the name of the cache entry for Opus built from `opus_path` via
+/// the CMake `config`.
+///
+/// The key covers the Opus source, the target, every setting recorded by
+/// `config`, the flags the `cmake` crate passes via `CMAKE_C_FLAGS`, the C
+/// compiler's path and version, and the environment variables configuring
+/// the build, including those only read by the `cmake` and `cc` crates.
+fn opus_cache_key(opus_path: &Path, config: &CMakeConfig) -> io::Result<String> {
+    const CONFIGURING_PREFIXES: &[&str] = &[
+        "LIBOPUS_",
+        "OPUS_",
+        "CC",
+        "CFLAGS",
+        "CMAKE",
+        "ANDROID_",
+        "EMSDK",
+        "AR",
+        "STRIP",
+        "MACOSX_DEPLOYMENT_TARGET",
+        "CARGO_ENCODED_RUSTFLAGS",
+        "SDKROOT",
+    ];
+    const IGNORED_VARIABLES: &[&str] = &["LIBOPUS_CACHE_DIR", "LIBOPUS_BUILD_VERBOSE"];
+
+    // The FNV-1a offset basis.
+    let hash = hash_dir(0xcbf2_9ce4_8422_2325, opus_path)?;
+
+    let hash = config
+        .settings
+        .iter()
+        .fold(hash, |hash, setting| fnv1a_hash(hash, setting.as_bytes()));
+
+    let hash = compiler_identity()?
+        .iter()
+        .fold(hash, |hash, part| fnv1a_hash(hash, part.as_bytes()));
+
+    let mut variables = env::vars()
+        .filter(|(name, _)| {
+            name.starts_with("CARGO_FEATURE_")
+                || name == "TARGET"
+                || name == "CARGO_CFG_TARGET_FEATURE"
+                || CONFIGURING_PREFIXES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+        })
+        .filter(|(name, _)| !IGNORED_VARIABLES.contains(&name.as_str()))
+        .collect::<Vec<_>>();
+    variables.sort();
+
+    let hash = variables.iter().fold(hash, |hash, (name, value)| {
+        fnv1a_hash(fnv1a_hash(hash, name.as_bytes()), value.as_bytes())
+    });
+
+    let target = env::var("TARGET").unwrap_or_default();
+
+    Ok(format!("{}-{:016x}", target, hash))
+}
+
+/// Returns the C compiler's path, the flags the `cc` crate resolves for it,
+/// which the `cmake` crate passes on via `CMAKE_C_FLAGS`, and its reported
+/// version.
+///
+/// MSVC's `cl` reports its version when run without arguments, other
+/// compilers via `--version`.
+fn compiler_identity() -> io::Result<Vec<String>> {
+    let compiler = cc::Build::new()
+        .cargo_metadata(false)
+        .try_get_compiler()
+        .map_err(io::Error::other)?;
+
+    let mut version = Command::new(compiler.path());
+
+    if !compiler.is_like_msvc() {
+        version.arg("--version");
+    }
+
+    let version = version.output()?;
+
+    let mut identity = vec![compiler.path().display().to_string()];
+    identity.extend(
+        compiler
+            .args()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned()),
+    );
+    identity.push(String::from_utf8_lossy(&version.stdout).into_owned());
+    identity.push(String::from_utf8_lossy(&version.stderr).into_owned());
+
+    Ok(identity)
+}
+
+/// Copies the directories of `source` to `destination`, except `skipped`.
+fn copy_dir(source: &Path, destination: &Path, skipped: &[&str]) -> io::Result<()> {
+    fs::create_dir_all(destination)?;
+
+    for entry in source.read_dir()? {
+        let entry = entry?;
+
+        if skipped.iter().any(|skipped| entry.file_name() == *skipped) {
+            continue;
+        }
+
+        let destination = destination.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &destination, &[])?;
+        } else {
+            fs::copy(entry.path(), destination)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Stores the installed Opus of `opus_build_dir` at `cache_entry`.
+///
+/// Opus is copied next to the entry first and then renamed, so that concurrent
+/// builds never see a partial entry. CMake's intermediate `build` directory is
+/// not stored.
+fn store_cached_opus(opus_build_dir: &Path, cache_entry: &Path) {
+    let entry_name = cache_entry
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let staging_dir = cache_entry.with_file_name(format!("{}.tmp{}", entry_name, process::id()));
+
+    let stored = copy_dir(opus_build_dir, &staging_dir, &["build"])
+        .and_then(|()| fs::rename(&staging_dir, cache_entry));
+
+    match stored {
+        Ok(()) => info!("Cached Opus at `{}`.", cache_entry.display()),
+        Err(why) => {
+            let _ = fs::remove_dir_all(&staging_dir);
+
+            // Another build may have stored the same entry in the meantime.
+            if cache_entry.is_dir() {
+                return;
+            }
+
+            println!(
+                "cargo:warning=Could not cache Opus at `{}`: {}",
+                cache_entry.display(),
+                why
+            );
+        }
+    }
+}
+
+/// Returns the `rustc-link-lib` kind, including modifiers, to link Opus with.
+///
+/// Setting `LIBOPUS_WHOLE_ARCHIVE` links every object of a static Opus, so that
+/// its symbols are kept when re-exported from a `cdylib`. Link modifiers are
+/// stable since Rust 1.61, which edition 2024 exceeds.
+fn link_kind(is_static: bool) -> &'static str {
+    let is_whole_archive = env_var("LIBOPUS_WHOLE_ARCHIVE").is_some();
+
+    if is_whole_archive && !is_static {
+        println!("cargo:warning=`LIBOPUS_WHOLE_ARCHIVE` only applies to static linking.");
+    }
+
+    if is_whole_archive && is_static {
+        // Bundling a whole archive into the `rlib` would link it twice.
+        "static:+whole-archive,-bundle"
+    } else {
+        rustc_linking_word(is_static)
+    }
+}
+
+/// Links the Opus found at `opus_root` and exposes `opus_root` and
+/// `include_dir` as `DEP_OPUS_ROOT` and `DEP_OPUS_INCLUDE` to dependent crates.
+fn link_opus(is_static: bool, opus_root: &Path, include_dir: &Path) -> Result<(), BuildError> {
+    let is_static_text = rustc_linking_word(is_static);
+
+    decision!(
+        "Linking Opus as {} lib: {}",
+        is_static_text,
+        opus_root.display()
+    );
+
+    let library_dirs = library_dir_candidates(opus_root);
+    let mut found_library = None;
+
+    for library_dir in library_dirs.iter().filter(|dir| dir.is_dir()) {
+        println!("cargo:rustc-link-search=native={}", library_dir.display());
+
+        found_library = found_library.or_else(|| {
+            opus_library_name(library_dir).map(|library_name| (library_dir.clone(), library_name))
+        });
+    }
+
+    if found_library.is_none() {
+        let Some(library_dir) = search_library_dir(opus_root) else {
+            return Err(BuildError::LibraryNotFound(library_dirs));
+        };
+
+        info!("Found Opus library in `{}`.", library_dir.display());
+        println!("cargo:rustc-link-search=native={}", library_dir.display());
+
+        found_library =
+            opus_library_name(&library_dir).map(|library_name| (library_dir, library_name));
+    }
+
+    let (library_dir, library_name) = found_library.unzip();
+
+    let library_name = link_name_override()?
+        .or(library_name)
+        .unwrap_or_else(|| String::from("opus"));
+
+    println!(
+        "cargo:rustc-link-lib={}={}",
+        link_kind(is_static),
+        library_name
+    );
+
+    if !is_static && target_cfg("target_os") == "windows" {
+        copy_opus_dll(opus_root, &library_dirs);
+    }
+
+    if let Some(library_dir) = library_dir
+        && !is_static
+    {
+        emit_rpath(&library_dir);
+    }
+
+    if is_static {
+        link_static_dependencies();
+    }
+
+    println!("cargo:root={}", opus_root.display());
+    println!("cargo:include={}", include_dir.display());
+
+    Ok(())
+}
+
+/// Embeds a run-time search path for the dynamic Opus in `library_dir` if
+/// `LIBOPUS_SET_RPATH` is set, so that it is found without `LD_LIBRARY_PATH`.
+///
+/// `LIBOPUS_SET_RPATH` set to a path, e.g. `$ORIGIN/../lib`, is used as is,
+/// `ON` uses `library_dir`. The link argument only applies to this package's
+/// targets, dependent crates receive the path as `DEP_OPUS_RPATH` to embed it
+/// themselves.
+fn emit_rpath(library_dir: &Path) {
+    let Some(rpath) = env_var("LIBOPUS_SET_RPATH") else {
+        return;
+    };
+
+    let is_unix = target_cfg("target_family")
+        .split(',')
+        .any(|family| family == "unix");
+
+    if !is_unix {
+        println!("cargo:warning=`LIBOPUS_SET_RPATH` only applies to Unix targets.");
+
+        return;
+    }
+
+    // A staged Opus is found at its final location at run time.
+    let library_dir = env_var("LIBOPUS_STAGING_PREFIX")
+        .map(PathBuf::from)
+        .and_then(|staging_prefix| {
+            library_dir
+                .strip_prefix(staged_path(&staging_prefix)?)
+                .ok()
+                .map(|relative_dir| staging_prefix.join(relative_dir))
+        })
+        .unwrap_or_else(|| library_dir.to_path_buf());
+
+    let rpath = match rpath.to_ascii_lowercase().as_str() {
+        "" | "1" | "on" | "true" | "yes" => library_dir.display().to_string(),
+        _ => rpath,
+    };
+
+    info!("Setting the run-time search path {}.", rpath);
+    println!("cargo:rustc-link-arg=-Wl,-rpath,{}", rpath);
+    println!("cargo:rpath={}", rpath);
+}
+
+/// Links the system libraries a static Opus depends on.
+///
+/// A static archive does not carry its dependencies, thus Opus' use of the
+/// maths library must be resolved when linking the final artifact. On Apple
+/// targets, `libm` is part of `libSystem` and linked anyway. Opus does not
+/// spawn threads, so `pthread` is not needed.
+fn link_static_dependencies() {
+    let is_unix = target_cfg("target_family")
+        .split(',')
+        .any(|family| family == "unix");
+
+    if is_unix && target_cfg("target_vendor") != "apple" {
+        println!("cargo:rustc-link-lib=m");
+    }
+}
+
+/// Lists the directories below `opus_root` that may contain the library.
+///
+/// Next to the plain `lib`, distributions use `lib64` or multiarch directories
+/// such as `lib/x86_64-linux-gnu`.
+fn library_dir_candidates(opus_root: &Path) -> Vec<PathBuf> {
+    let lib_dir = opus_root.join("lib");
+    let mut candidates = vec![lib_dir.clone(), opus_root.join("lib64")];
+
+    if let Ok(target) = env::var("TARGET") {
+        candidates.push(lib_dir.join(target));
+    }
+
+    let target_env = target_cfg("target_env");
+
+    if !target_env.is_empty() {
+        candidates.push(lib_dir.join(format!(
+            "{}-{}-{}",
+            target_cfg("target_arch"),
+            target_cfg("target_os"),
+            target_env
+        )));
+    }
+
+    candidates
+}
+
+/// Searches the directories below `opus_root` breadth-first for the library.
+///
+/// Depending on the CMake version and generator, the library ends up in e.g.
+/// `build` or `build/Release` instead of `lib`. As the install tree is shallower
+/// than the build tree, it is preferred. The depth is limited to keep the search
+/// of large prefixes cheap.
+fn search_library_dir(opus_root: &Path) -> Option<PathBuf> {
+    const MAX_DEPTH: usize = 3;
+
+    let mut current_level = vec![opus_root.to_path_buf()];
+
+    for _ in 0..=MAX_DEPTH {
+        if let Some(library_dir) = current_level.iter().find(|dir| contains_opus_library(dir)) {
+            return Some(library_dir.clone());
+        }
+
+        current_level = current_level
+            .iter()
+            .filter_map(|dir| dir.read_dir().ok())
+            .flat_map(|entries| entries.filter_map(Result::ok))
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+    }
+
+    None
+}
+
+/// Copies the Opus DLL next to the final artifacts, so that executables
+/// linking Opus dynamically on Windows can be run without adjusting `PATH`.
+///
+/// CMake installs DLLs into `bin`, import libraries into `lib`.
+fn copy_opus_dll(opus_root: &Path, library_dirs: &[PathBuf]) {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("Cargo did not set `OUT_DIR`"));
+
+    let opus_dll = std::iter::once(opus_root.join("bin"))
+        .chain(library_dirs.iter().cloned())
+        .filter_map(|dir| dir.read_dir().ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .map(|entry| entry.path())
+        .find(|path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+            file_name.contains("opus") && file_name.ends_with(".dll")
+        });
+
+    let Some(opus_dll) = opus_dll else {
+        println!(
+            "cargo:warning=Only found an import library for Opus in `{}`, \
+             make sure `opus.dll` can be found at runtime.",
+            opus_root.display()
+        );
+
+        return;
+    };
+
+    // `OUT_DIR` is `<profile>/build/<package>/out`, the profile directory
+    // contains the final artifacts.
+    let target_dirs = [Some(out_dir.as_path()), out_dir.ancestors().nth(3)];
+    let dll_name = opus_dll.file_name().expect("DLL path has a file name");
+
+    for target_dir in target_dirs.into_iter().flatten() {
+        let destination = target_dir.join(dll_name);
+
+        if let Err(why) = fs::copy(&opus_dll, &destination) {
+            println!(
+                "cargo:warning=Could not copy `{}` to `{}`: {}",
+                opus_dll.display(),
+                destination.display(),
+                why
+            );
+        } else {
+            info!("Copied Opus DLL to `{}`.", destination.display());
+        }
+    }
+}
+
+/// Returns the name set via `LIBOPUS_LINK_NAME`, or its older spelling
+/// `LIBOPUS_LIB_NAME`, to link instead of the detected Opus library.
+///
+/// The name is passed to `rustc-link-lib` as is, thus it must not contain a
+/// path, link kind, or modifiers.
+fn link_name_override() -> Result<Option<String>, BuildError> {
+    let Some(link_name) = env_var("LIBOPUS_LINK_NAME").or_else(|| env_var("LIBOPUS_LIB_NAME"))
+    else {
+        return Ok(None);
+    };
+
+    let is_plausible = !link_name.is_empty()
+        && !link_name.starts_with('-')
+        && link_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+'));
+
+    if !is_plausible {
+        return Err(BuildError::InvalidLinkName(link_name));
+    }
+
+    decision!("Linking Opus as `{}`.", link_name);
+
+    Ok(Some(link_name))
+}
+
+/// Returns whether `dir` contains a static, shared, or import library of Opus.
+fn contains_opus_library(dir: &Path) -> bool {
+    opus_library_name(dir).is_some()
+}
+
+/// Returns the name to pass to `rustc-link-lib` for the Opus library in `dir`.
+///
+/// Unix-like libraries such as `libopus.a` are linked as `opus`. On MSVC, the
+/// `lib`-prefix is part of the name, thus vcpkg's `libopus.lib` is linked as
+/// `libopus`, while CMake's `opus.lib` is linked as `opus`.
+fn opus_library_name(dir: &Path) -> Option<String> {
+    let file_names = dir
+        .read_dir()
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+
+    let has_file = |name: &str| file_names.iter().any(|file_name| file_name == name);
+
+    // A `libopus.dll` next to `libopus.lib` must not be mistaken for a
+    // Unix-like library, thus MSVC's names are checked first.
+    if has_file("opus.lib") {
+        Some(String::from("opus"))
+    } else if has_file("libopus.lib") {
+        Some(String::from("libopus"))
+    } else if file_names
+        .iter()
+        .any(|file_name| file_name.starts_with("libopus."))
+    {
+        Some(String::from("opus"))
+    } else {
+        None
+    }
+}
+
+/// Parses the major and minor version of an Opus version such as `1.5.2`.
+fn major_minor_version(version: &str) -> Option<(u32, u32)> {
+    let mut components = version.trim().split('.');
+    let major = components.next()?.parse().ok()?;
+    let minor = components.next()?.parse().ok()?;
+
+    Some((major, minor))
+}
+
+/// Detects the Opus version from the headers in `include_dir`.
+///
+/// Opus does not declare its version in its headers, thus the version is
+/// derived from the newest request the headers define.
+fn detect_header_version(include_dir: &Path) -> Option<String> {
+    let opus_defines = fs::read_to_string(include_dir.join("opus_defines.h")).ok()?;
+
+    let version = if opus_defines.contains("OPUS_SET_DRED_DURATION_REQUEST") {
+        "1.5"
+    } else if opus_defines.contains("OPUS_GET_IN_DTX_REQUEST") {
+        "1.4"
+    } else {
+        "1.3"
+    };
+
+    Some(version.to_string())
+}
+
+/// Reads the version of the Opus below `opus_root` from its `opus.pc`, which
+/// CMake installs next to the library.
+fn detect_pkg_config_file_version(opus_root: &Path) -> Option<String> {
+    library_dir_candidates(opus_root)
+        .iter()
+        .filter_map(|library_dir| {
+            fs::read_to_string(library_dir.join("pkgconfig").join("opus.pc")).ok()
+        })
+        .find_map(|pkg_config_file| {
+            pkg_config_file
+                .lines()
+                .find_map(|line| line.strip_prefix("Version:"))
+                .map(|version| version.trim().to_string())
+        })
+}
+
+/// Detects the version of the Opus below `opus_root`, falling back to the
+/// headers in `include_dir` if Opus has no `opus.pc`.
+fn detect_opus_version(opus_root: &Path, include_dir: &Path) -> Option<String> {
+    detect_pkg_config_file_version(opus_root).or_else(|| detect_header_version(include_dir))
+}
+
+/// Exposes the linked Opus version and linking mode to `src/lib.rs` via
+/// `AUDIOPUS_OPUS_VERSION` and `AUDIOPUS_OPUS_LINKING`, and to dependent build
+/// scripts via `DEP_OPUS_VERSION`.
+fn emit_version_env(opus_version: Option<&str>, is_static: bool) {
+    let opus_version = opus_version.unwrap_or("unknown");
+    decision!("Using Opus {}.", opus_version);
+
+    println!("cargo:rustc-env=AUDIOPUS_OPUS_VERSION={}", opus_version);
+    println!(
+        "cargo:rustc-env=AUDIOPUS_OPUS_LINKING={}",
+        if is_static { "static" } else { "dynamic" }
+    );
+    println!("cargo:version={}", opus_version);
+}
+
+/// Emits the `opus_static` or `opus_dynamic` `cfg` according to `is_static`,
+/// and exposes the linking mode to dependent build scripts via
+/// `DEP_OPUS_LINKING`, as `cfg`s do not propagate to dependent crates.
+fn emit_linking_cfg(is_static: bool) {
+    let linking = if is_static { "static" } else { "dynamic" };
+
+    println!("cargo:rustc-check-cfg=cfg(opus_static, opus_dynamic)");
+    println!("cargo:rustc-cfg=opus_{}", linking);
+    println!("cargo:linking={}", linking);
+}
+
+/// Emits a `cfg` for every Opus version the linked `opus_version` is at least,
+/// e.g. `opus_1_4` and `opus_1_5` for Opus 1.5.
+///
+/// The pre-generated binding guards declarations newer than Opus 1.3 behind
+/// these `cfg`s. If the version is unknown, the binding's version is assumed.
+fn emit_version_cfgs(opus_version: Option<&str>) {
+    const VERSION_CFGS: &[((u32, u32), &str)] = &[((1, 4), "opus_1_4"), ((1, 5), "opus_1_5")];
+
+    let opus_version = opus_version
+        .and_then(major_minor_version)
+        .or_else(|| major_minor_version(BINDING_OPUS_VERSION));
+
+    for (cfg_version, cfg_name) in VERSION_CFGS {
+        println!("cargo:rustc-check-cfg=cfg({})", cfg_name);
+
+        if opus_version.is_some_and(|opus_version| opus_version >= *cfg_version) {
+            println!("cargo:rustc-cfg={}", cfg_name);
+        }
+    }
+}
+
+/// Returns a representative set of the functions the binding declares for
+/// the linked `opus_version` and the enabled features.
+fn probed_symbols(opus_version: Option<&str>) -> Vec<&'static str> {
+    let mut symbols = vec![
+        "opus_get_version_string",
+        "opus_strerror",
+        "opus_encoder_create",
+        "opus_encode",
+        "opus_decoder_create",
+        "opus_decode",
+        "opus_packet_get_nb_samples",
+        "opus_repacketizer_create",
+        "opus_multistream_encoder_create",
+        "opus_multistream_decoder_create",
+    ];
+
+    if !cfg!(feature = "no-float-api") {
+        symbols.extend(["opus_encode_float", "opus_decode_float"]);
+    }
+
+    let opus_version = opus_version
+        .and_then(major_minor_version)
+        .or_else(|| major_minor_version(BINDING_OPUS_VERSION));
+
+    if opus_version.is_some_and(|opus_version| opus_version >= (1, 5)) {
+        symbols.extend(["opus_dred_decoder_create", "opus_dred_parse"]);
+    }
+
+    if cfg!(feature = "custom") {
+        symbols.extend(["opus_custom_mode_create", "opus_custom_encoder_create"]);
+    }
+
+    symbols
+}
+
+/// Links a minimal C program referencing the [`probed_symbols`] against the
+/// `linked_opus` if `LIBOPUS_VERIFY_SYMBOLS` is set, so that an Opus lacking
+/// declared functions fails this build instead of dependent crates' linking.
+fn verify_symbols(linked_opus: &LinkedOpus, is_static: bool) -> Result<(), BuildError> {
+    if !env_flag("LIBOPUS_VERIFY_SYMBOLS").unwrap_or(false) {
+        return Ok(());
+    }
+
+    // `pkg-config` omits the system directories from its link paths, thus its
+    // flags are passed on as they are.
+    #[cfg(any(unix, target_env = "gnu"))]
+    let pkg_config_library = if linked_opus.discovery == Discovery::PkgConfig {
+        probe_pkg_config(&["opus"], is_static)
+            .ok()
+            .map(|mut libraries| libraries.remove(0))
+    } else {
+        None
+    };
+    #[cfg(not(any(unix, target_env = "gnu")))]
+    let pkg_config_library: Option<pkg_config::Library> = None;
+
+    if pkg_config_library.is_none() && linked_opus.library_dir.is_none() {
+        println!(
+            "cargo:warning=Not verifying the symbols of Opus {}, its library directory is \
+             unknown.",
+            linked_opus.discovery
+        );
+
+        return Ok(());
+    }
+
+    let symbols = probed_symbols(linked_opus.version.as_deref());
+
+    // Only the addresses are taken, thus any prototype resolves the symbols.
+    let probe_source = format!(
+        "typedef void (*symbol)(void);\n{}\nstatic const symbol symbols[] = {{ {} }};\n\
+         int main(void) {{ return symbols[0] == 0; }}\n",
+        symbols
+            .iter()
+            .map(|symbol| format!("extern void {}(void);", symbol))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        symbols.join(", ")
+    );
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("Cargo did not set `OUT_DIR`"));
+    let probe_dir = out_dir.join("symbol-probe");
+    let probe_error = |why: io::Error| BuildError::MissingSymbols(Vec::new(), why.to_string());
+
+    fs::create_dir_all(&probe_dir).map_err(probe_error)?;
+    fs::write(probe_dir.join("probe.c"), probe_source).map_err(probe_error)?;
+
+    let compiler = cc::Build::new()
+        .cargo_metadata(false)
+        .try_get_compiler()
+        .map_err(|why| BuildError::MissingSymbols(Vec::new(), why.to_string()))?;
+
+    let mut probe = compiler.to_command();
+    probe.current_dir(&probe_dir).arg("probe.c");
+
+    if let Some(library) = &pkg_config_library {
+        probe
+            .args(["-o", "probe"])
+            .args(
+                library
+                    .link_paths
+                    .iter()
+                    .map(|link_path| format!("-L{}", link_path.display())),
+            )
+            .args(library.libs.iter().map(|lib| format!("-l{}", lib)));
+    } else if let Some(library_dir) = &linked_opus.library_dir {
+        let library_name = link_name_override()?
+            .or_else(|| opus_library_name(library_dir))
+            .unwrap_or_else(|| String::from("opus"));
+
+        if linked_opus.discovery == Discovery::Framework {
+            // `library_dir` is the framework itself, e.g. `Opus.framework`.
+            let framework_name = library_dir.file_stem().map_or_else(
+                || String::from("Opus"),
+                |name| name.to_string_lossy().into_owned(),
+            );
+
+            probe.args(["-o", "probe"]);
+
+            if let Some(frameworks) = library_dir.parent() {
+                probe.arg(format!("-F{}", frameworks.display()));
+            }
+
+            probe.arg("-framework").arg(framework_name);
+        } else if compiler.is_like_msvc() {
+            probe
+                .arg("/Feprobe.exe")
+                .arg("/link")
+                .arg(format!("/LIBPATH:{}", library_dir.display()))
+                .arg(format!("{}.lib", library_name));
+        } else {
+            probe
+                .args(["-o", "probe"])
+                .arg(format!("-L{}", library_dir.display()))
+                .arg(format!("-l{}", library_name));
+
+            if is_static && target_cfg("target_vendor") != "apple" {
+                probe.arg("-lm");
+            }
+        }
+    }
+
+    info!("Verifying {} Opus symbols.", symbols.len());
+
+    let output = probe.output().map_err(probe_error)?;
+
+    if output.status.success() {
+        info!("Verified the Opus symbols.");
+
+        return Ok(());
+    }
+
+    let linker_output = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // `opus_encode` must not be reported for a missing `opus_encode_float`.
+    let missing_symbols = symbols
+        .into_iter()
+        .filter(|symbol| {
+            linker_output
+                .split(|character: char| !character.is_ascii_alphanumeric() && character != '_')
+                .any(|word| word == *symbol)
+        })
+        .collect();
+
+    Err(BuildError::MissingSymbols(
+        missing_symbols,
+        linker_output.trim().to_string(),
+    ))
+}
+
+/// Checks that the linked `opus_version` declares every item of the
+/// pre-generated binding not guarded by a version `cfg`.
+///
+/// An older Opus would fail to link with missing symbols, thus this warns, or
+/// errors if `LIBOPUS_STRICT_VERSION` is set.
+fn check_minimum_version(opus_version: Option<&str>) -> Result<(), BuildError> {
+    if cfg!(feature = "generate_binding") {
+        return Ok(());
+    }
+
+    let Some(opus_version) = opus_version else {
+        return Ok(());
+    };
+
+    let is_supported = major_minor_version(opus_version).is_none_or(|major_minor| {
+        Some(major_minor) >= major_minor_version(BINDING_MIN_OPUS_VERSION)
+    });
+
+    if is_supported {
+        return Ok(());
+    }
+
+    if env_var("LIBOPUS_STRICT_VERSION").is_some() {
+        return Err(BuildError::OpusTooOld(opus_version.to_string()));
+    }
+
+    println!(
+        "cargo:warning=The linked Opus {} is older than Opus {}, the oldest the \
+         pre-generated binding supports, using missing functions will fail to link.",
+        opus_version, BINDING_MIN_OPUS_VERSION
+    );
+
+    Ok(())
+}
+
+/// Warns if the pre-generated binding was made for another Opus version than
+/// the `system_version` being linked.
+#[cfg_attr(not(any(unix, target_env = "gnu")), allow(dead_code))]
+fn check_binding_version(system_version: &str) {
+    if cfg!(feature = "generate_binding") {
+        return;
+    }
+
+    if major_minor_version(system_version) != major_minor_version(BINDING_OPUS_VERSION) {
+        println!(
+            "cargo:warning=The system's Opus {} differs from Opus {} the pre-generated \
+             binding was made for, consider enabling the `generate_binding`-feature.",
+            system_version, BINDING_OPUS_VERSION
+        );
+    }
+}
+
+/// Probes for Opus via `pkg-config`.
+///
+/// When linking statically, the found Opus is only used if it provides a
+/// static library, as `pkg-config` silently links dynamically otherwise.
+///
+/// If `LIBOPUS_MIN_VERSION` is set, an older Opus is ignored as well.
+#[cfg(any(unix, target_env = "gnu"))]
+fn find_via_pkg_config(is_static: bool) -> Option<pkg_config::Library> {
+    // Probe without emitting link directives first, they cannot be revoked.
+    let library = probe_pkg_config(&["opus"], is_static).ok()?.remove(0);
+
+    if is_static && !has_static_library(&library) {
+        println!(
+            "cargo:warning=`pkg-config` found Opus {} without a static library, \
+             ignoring it to link statically.",
+            library.version
+        );
+
+        return None;
+    }
+
+    if let Some(min_version) = env_var("LIBOPUS_MIN_VERSION") {
+        let is_recent_enough = pkg_config::Config::new()
+            .statik(is_static)
+            .cargo_metadata(false)
+            .atleast_version(&min_version)
+            .probe("opus")
+            .is_ok();
+
+        if !is_recent_enough {
+            println!(
+                "cargo:warning=`pkg-config` found Opus {}, which is older than the \
+                 required {}, ignoring it.",
+                library.version, min_version
+            );
+
+            return None;
+        }
+    }
+
+    link_pkg_config_libraries(std::slice::from_ref(&library), is_static, &[]);
+
+    if let Some(include_dir) = library.include_paths.first() {
+        println!("cargo:include={}", include_dir.display());
+    }
+
+    check_binding_version(&library.version);
+
+    Some(library)
+}
+
+/// Probes every `pkg-config` module in `modules` without emitting any link
+/// directives, returning them in the same order.
+///
+/// When linking statically, a module's `libdir` providing its static library
+/// is added to its link paths, see
+/// [`static_library_dirs`](static_library::static_library_dirs).
+#[cfg(any(unix, target_env = "gnu"))]
+fn probe_pkg_config(
+    modules: &[&str],
+    is_static: bool,
+) -> Result<Vec<pkg_config::Library>, pkg_config::Error> {
+    let mut config = pkg_config::Config::new();
+    config.statik(is_static).cargo_metadata(false);
+
+    modules
+        .iter()
+        .map(|module| {
+            let mut library = config.probe(module)?;
+
+            if is_static {
+                let libdir = pkg_config::get_variable(module, "libdir").ok();
+                library.link_paths = static_library::static_library_dirs(
+                    &library.link_paths,
+                    libdir.as_deref().map(Path::new),
+                    &library.libs,
+                );
+            }
+
+            Ok(library)
+        })
+        .collect()
+}
+
+/// Returns the prefixes packages are installed to on the BSD `target_os`,
+/// i.e. ports on OpenBSD and pkgsrc on NetBSD.
+#[cfg(any(unix, target_env = "gnu"))]
+fn bsd_package_prefixes(target_os: &str) -> &'static [&'static str] {
+    match target_os {
+        "openbsd" => &["/usr/local"],
+        "netbsd" => &["/usr/pkg", "/usr/local"],
+        _ => &[],
+    }
+}
+
+/// Returns the BSD package prefix providing the Opus library matching the
+/// linking method, for an Opus installed without `opus.pc`.
+#[cfg(any(unix, target_env = "gnu"))]
+fn find_via_bsd_packages(is_static: bool) -> Option<PathBuf> {
+    bsd_package_prefixes(&target_cfg("target_os"))
+        .iter()
+        .map(PathBuf::from)
+        .find(|prefix| {
+            let library_dir = prefix.join("lib");
+
+            let has_library = if is_static {
+                library_dir.join("libopus.a").is_file()
+            } else {
+                contains_opus_library(&library_dir)
+            };
+
+            has_library && prefix.join("include").join("opus").join("opus.h").is_file()
+        })
+}
+
+/// Links `libraries` found via `pkg-config` in the order of their
+/// dependencies, except for those named in `skipped_libs`.
+///
+/// Each library links its dependencies after itself, thus only the last
+/// occurrence of a library named by several is kept, e.g. `opusfile`, `ogg`,
+/// and `opusenc` before a shared `opus`.
+fn link_pkg_config_libraries(
+    libraries: &[pkg_config::Library],
+    is_static: bool,
+    skipped_libs: &[&str],
+) {
+    let mut link_paths: Vec<&Path> = Vec::new();
+
+    for link_path in libraries.iter().flat_map(|library| &library.link_paths) {
+        if !link_paths.contains(&link_path.as_path()) {
+            println!("cargo:rustc-link-search=native={}", link_path.display());
+            link_paths.push(link_path);
+        }
+    }
+
+    for framework_path in libraries
+        .iter()
+        .flat_map(|library| &library.framework_paths)
+    {
+        println!(
+            "cargo:rustc-link-search=framework={}",
+            framework_path.display()
+        );
+    }
+
+    let libs: Vec<&str> = libraries
+        .iter()
+        .flat_map(|library| &library.libs)
+        .map(String::as_str)
+        .filter(|lib| !skipped_libs.contains(lib))
+        .collect();
+
+    for (index, lib) in libs.iter().enumerate() {
+        if libs[index + 1..].contains(lib) {
+            continue;
+        }
+
+        // Libraries in the system directories are omitted by `pkg-config`
+        // and left for the linker to pick.
+        let has_static_library = link_paths
+            .iter()
+            .any(|link_path| static_library::contains_static_library(link_path, lib));
+
+        if is_static && has_static_library {
+            println!("cargo:rustc-link-lib=static={}", lib);
+        } else {
+            println!("cargo:rustc-link-lib={}", lib);
+        }
+    }
+
+    for framework in libraries.iter().flat_map(|library| &library.frameworks) {
+        println!("cargo:rustc-link-lib=framework={}", framework);
+    }
+}
+
+/// Returns whether the `pkg-config` result contains a static Opus library.
+#[cfg(any(unix, target_env = "gnu"))]
+fn has_static_library(library: &pkg_config::Library) -> bool {
+    library
+        .link_paths
+        .iter()
+        .any(|link_path| static_library::contains_static_library(link_path, "opus"))
+}
+
+/// Returns whether the target links Opus statically by default, see
+/// [`is_static_by_default`](linking::is_static_by_default).
+///
+/// The build script is compiled for the host, thus the target is inspected at
+/// runtime.
+fn default_library_linking() -> bool {
+    let is_unix = target_cfg("target_family")
+        .split(',')
+        .any(|family| family == "unix");
+
+    linking::is_static_by_default(&target_cfg("target_os"), &target_cfg("target_env"), is_unix)
+}
+
+/// Returns the `Opus.framework` set via `LIBOPUS_FRAMEWORK`, pointing to
+/// either the framework or the directory containing it, or otherwise the one
+/// installed to `/Library/Frameworks` or `~/Library/Frameworks`.
+///
+/// Frameworks are linked dynamically, thus only a framework set explicitly is
+/// used when linking statically.
+fn find_opus_framework(is_static: bool) -> Option<PathBuf> {
+    if let Some(framework) = env_var("LIBOPUS_FRAMEWORK") {
+        let framework = PathBuf::from(framework);
+        let framework = if framework.extension() == Some(OsStr::new("framework")) {
+            framework
+        } else {
+            framework.join("Opus.framework")
+        };
+
+        if !framework.is_dir() {
+            println!(
+                "cargo:warning=`LIBOPUS_FRAMEWORK` does not point to an `Opus.framework`: {}",
+                framework.display()
+            );
+
+            return None;
+        }
+
+        if is_static {
+            println!(
+                "cargo:warning=Linking `{}` dynamically, frameworks cannot be linked statically.",
+                framework.display()
+            );
+        }
+
+        return Some(framework);
+    }
+
+    if is_static {
+        return None;
+    }
+
+    let user_frameworks =
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Frameworks"));
+
+    [Some(PathBuf::from("/Library/Frameworks")), user_frameworks]
+        .into_iter()
+        .flatten()
+        .map(|frameworks| frameworks.join("Opus.framework"))
+        .find(|framework| framework.is_dir())
+}
+
+/// Links the Opus `framework` via `-framework`, instead of `-l`.
+fn link_opus_framework(framework: &Path) -> LinkedOpus {
+    let framework_name = framework.file_stem().map_or_else(
+        || String::from("Opus"),
+        |name| name.to_string_lossy().into_owned(),
+    );
+
+    if let Some(frameworks) = framework.parent() {
+        println!("cargo:rustc-link-search=framework={}", frameworks.display());
+    }
+
+    println!("cargo:rustc-link-lib=framework={}", framework_name);
+
+    let include_dir = framework.join("Headers");
+
+    println!("cargo:root={}", framework.display());
+    println!("cargo:include={}", include_dir.display());
+
+    LinkedOpus {
+        discovery: Discovery::Framework,
+        library_dir: Some(framework.to_path_buf()),
+        version: detect_header_version(&include_dir),
+        include_dir,
+    }
+}
+
+/// Returns the pre-installed Opus set via `LIBOPUS_LIB_DIR` or `OPUS_LIB_DIR`.
+///
+/// If the directory does not contain an Opus library, a warning is emitted
+/// and `None` is returned, so that Opus is built instead.
+fn find_installed_opus() -> Option<PathBuf> {
+    let installed_opus = PathBuf::from(opus_env_var("LIB_DIR")?);
+
+    let has_library = library_dir_candidates(&installed_opus)
+        .iter()
+        .any(|library_dir| contains_opus_library(library_dir));
+
+    if has_library {
+        Some(installed_opus)
+    } else {
+        println!(
+            "cargo:warning=No Opus library found in `{}`, building Opus instead.",
+            installed_opus.display()
+        );
+
+        None
+    }
+}
+
+/// Looks for Opus installed via vcpkg, leaving the triplet to the
+/// `vcpkg`-crate, which honours `VCPKG_ROOT`, `VCPKGRS_TRIPLET`, and
+/// `VCPKGRS_DYNAMIC`.
+///
+/// Returns `None` if vcpkg lacks Opus or its triplet does not match
+/// `is_static`, otherwise the linker arguments have been emitted.
+#[cfg(feature = "vcpkg")]
+fn find_via_vcpkg(is_static: bool) -> Option<vcpkg::Library> {
+    let mut config = vcpkg::Config::new();
+    // The linker arguments are only emitted once the linking method matches.
+    config.cargo_metadata(false);
+
+    let library = match config.find_package("opus") {
+        Ok(library) => library,
+        Err(why) => {
+            info!("`vcpkg` could not find `Opus`: {}", why);
+
+            return None;
+        }
+    };
+
+    if library.is_static != is_static {
+        println!(
+            "cargo:warning=vcpkg's triplet `{}` provides a {} Opus, but Opus is linked as {} \
+             lib, building Opus instead. Set `VCPKGRS_TRIPLET` or `VCPKGRS_DYNAMIC` to pick \
+             another triplet.",
+            library.vcpkg_triplet,
+            rustc_linking_word(library.is_static),
+            rustc_linking_word(is_static)
+        );
+
+        return None;
+    }
+
+    for metadata in &library.cargo_metadata {
+        println!("{}", metadata);
+    }
+
+    Some(library)
+}
+
+/// Looks for Opus installed via Homebrew, `/opt/homebrew` on Apple silicon and
+/// `/usr/local` on Intel, or MacPorts, `/opt/local`.
+///
+/// Returns the prefix containing the headers and the static or dynamic
+/// library, depending on `is_static`.
+fn find_via_macos_package_manager(is_static: bool) -> Option<PathBuf> {
+    const PREFIXES: &[&str] = &["/opt/homebrew", "/usr/local", "/opt/local"];
+
+    let library_name = if is_static {
+        "libopus.a"
+    } else {
+        "libopus.dylib"
+    };
+
+    PREFIXES.iter().map(PathBuf::from).find(|prefix| {
+        prefix.join("lib").join(library_name).is_file()
+            && prefix.join("include").join("opus").join("opus.h").is_file()
+    })
+}
+
+/// Returns the Opus installed via `cmake --install` to the prefix set via
+/// `LIBOPUS_INSTALL_PREFIX` or `OPUS_INSTALL_PREFIX`.
+///
+/// The prefix must contain both the library and `include/opus/opus.h`.
+fn find_install_prefix() -> Result<Option<PathBuf>, BuildError> {
+    let Some(install_prefix) = opus_env_var("INSTALL_PREFIX") else {
+        return Ok(None);
+    };
+
+    let install_prefix = PathBuf::from(install_prefix);
+
+    let has_library = library_dir_candidates(&install_prefix)
+        .iter()
+        .any(|library_dir| contains_opus_library(library_dir));
+
+    if !has_library {
+        return Err(BuildError::InvalidInstallPrefix(
+            install_prefix,
+            "an Opus library in `lib`",
+        ));
+    }
+
+    if !install_prefix
+        .join("include")
+        .join("opus")
+        .join("opus.h")
+        .is_file()
+    {
+        return Err(BuildError::InvalidInstallPrefix(
+            install_prefix,
+            "`include/opus/opus.h`",
+        ));
+    }
+
+    Ok(Some(install_prefix))
+}
+
+/// Returns the directory containing `opus.h`.
+///
+/// `LIBOPUS_INCLUDE_DIR` or `OPUS_INCLUDE_DIR` point to the headers matching
+/// a pre-installed Opus, followed by the headers of the `install_prefix`.
+/// Otherwise the vendored `opus/include` is used.
+fn find_include_dir(install_prefix: Option<&Path>) -> PathBuf {
+    if let Some(include_directory) = opus_env_var("INCLUDE_DIR") {
+        PathBuf::from(include_directory)
+    } else if let Some(install_prefix) = install_prefix {
+        install_prefix.join("include").join("opus")
+    } else {
+        PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("Cargo did not set the manifest path"))
+            .join("opus")
+            .join("include")
+    }
+}
+
+/// Decides whether to link Opus statically, see
+/// [`decide_linking`](linking::decide_linking) for the precedence.
+fn is_static_build() -> bool {
+    let decision = linking::decide_linking(linking::LinkingInputs {
+        is_wasm: target_cfg("target_arch") == "wasm32",
+        is_static_env: opus_env_var("STATIC").is_some(),
+        is_dynamic_env: opus_env_var("DYNAMIC").is_some(),
+        is_static_feature: cfg!(feature = "static"),
+        is_dynamic_feature: cfg!(feature = "dynamic"),
+        is_static_by_default: default_library_linking(),
+    });
+
+    if let Some(conflict) = decision.conflict {
+        println!("cargo:warning={}", conflict);
+    }
+
+    info!("{}", decision.reason.describe());
+
+    decision.is_static
+}
+
+/// How the linked Opus was found.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Discovery {
+    PkgConfig,
+    #[cfg(any(unix, target_env = "gnu"))]
+    BsdPackage,
+    InstallPrefix,
+    PreInstalled,
+    #[cfg(feature = "vcpkg")]
+    Vcpkg,
+    Framework,
+    PackageManager,
+    BuiltFromSource,
+    NotLinkedOnDocsRs,
+    NotLinked,
+}
+
+impl fmt::Display for Discovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::PkgConfig => "pkg-config",
+            #[cfg(any(unix, target_env = "gnu"))]
+            Self::BsdPackage => "BSD package",
+            Self::InstallPrefix => "install prefix",
+            Self::PreInstalled => "pre-installed",
+            #[cfg(feature = "vcpkg")]
+            Self::Vcpkg => "vcpkg",
+            Self::Framework => "framework",
+            Self::PackageManager => "Homebrew or MacPorts",
+            Self::BuiltFromSource => "built from source",
+            Self::NotLinkedOnDocsRs => "not linked on docs.rs",
+            Self::NotLinked => "not linked due to `LIBOPUS_NO_LINK`",
+        })
+    }
+}
+
+/// The Opus that was linked, as reported via `LIBOPUS_PRINT_CONFIG`.
+struct LinkedOpus {
+    /// How Opus was found.
+    discovery: Discovery,
+    /// The directory containing the Opus library, if known.
+    library_dir: Option<PathBuf>,
+    /// The directory containing the Opus headers.
+    include_dir: PathBuf,
+    /// The version of Opus, if it could be detected.
+    version: Option<String>,
+}
+
+impl LinkedOpus {
+    /// Describes the Opus found via `discovery` below `opus_root`.
+    fn at(discovery: Discovery, opus_root: &Path, include_dir: &Path) -> Self {
+        Self {
+            discovery,
+            library_dir: find_library_dir(opus_root),
+            include_dir: include_dir.to_path_buf(),
+            version: detect_opus_version(opus_root, include_dir),
+        }
+    }
+}
+
+/// Returns the directory below `opus_root` containing the Opus library, like
+/// `link_opus` looks it up.
+fn find_library_dir(opus_root: &Path) -> Option<PathBuf> {
+    library_dir_candidates(opus_root)
+        .into_iter()
+        .find(|dir| contains_opus_library(dir))
+        .or_else(|| search_library_dir(opus_root))
+}
+
+/// Emits the resolved build configuration as a single block of warnings if
+/// `LIBOPUS_PRINT_CONFIG` is set, e.g. to paste it into a bug report.
+fn print_config(linked_opus: &LinkedOpus, is_static: bool) {
+    if env_var("LIBOPUS_PRINT_CONFIG").is_none() {
+        return;
+    }
+
+    let unknown = || String::from("unknown");
+
+    let lines = [
+        String::from("Resolved Opus build configuration:"),
+        format!(
+            "  target: {}",
+            env::var("TARGET").unwrap_or_else(|_| unknown())
+        ),
+        format!(
+            "  linking: {}",
+            if is_static { "static" } else { "dynamic" }
+        ),
+        format!("  discovery: {}", linked_opus.discovery),
+        format!(
+            "  library directory: {}",
+            linked_opus
+                .library_dir
+                .as_ref()
+                .map_or_else(unknown, |dir| dir.display().to_string())
+        ),
+        format!("  include directory: {}", linked_opus.include_dir.display()),
+        format!(
+            "  version: {}",
+            linked_opus.version.clone().unwrap_or_else(unknown)
+        ),
+    ];
+
+    for line in lines {
+        println!("cargo:warning={}", line);
+    }
+}
+
+/// Finds or builds Opus and links it.
+///
+/// Returns the linked Opus version, if it could be detected.
+fn find_and_link_opus(
+    is_static: bool,
+    include_dir: &Path,
+    install_prefix: Option<&Path>,
+) -> Result<LinkedOpus, BuildError> {
+    // Only an explicitly configured Opus is used, otherwise Opus is built.
+    let is_discovering = opus_env_var("NO_PKG").is_none();
+
+    if !is_discovering {
+        decision!(
+            "Bypassed discovering Opus via `pkg-config`, vcpkg, and package managers as \
+             `LIBOPUS_NO_PKG` or `OPUS_NO_PKG` is set."
+        );
+    }
+
+    #[cfg(any(unix, target_env = "gnu"))]
+    if is_discovering {
+        if let Some(library) = find_via_pkg_config(is_static) {
+            decision!("Found `Opus` via `pkg_config`.");
+
+            return Ok(LinkedOpus {
+                discovery: Discovery::PkgConfig,
+                library_dir: library.link_paths.first().cloned(),
+                include_dir: library
+                    .include_paths
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| include_dir.to_path_buf()),
+                version: Some(library.version),
+            });
+        } else {
+            info!("`pkg_config` could not find `Opus`.");
+
+            if let Some(package_opus) = find_via_bsd_packages(is_static) {
+                decision!(
+                    "Found `Opus` installed as package at `{}`.",
+                    package_opus.display()
+                );
+
+                let include_dir = package_opus.join("include").join("opus");
+                link_opus(is_static, &package_opus, &include_dir)?;
+
+                return Ok(LinkedOpus::at(
+                    Discovery::BsdPackage,
+                    &package_opus,
+                    &include_dir,
+                ));
+            }
+        }
+    }
+
+    if let Some(install_prefix) = install_prefix {
+        decision!("Using Opus installed to `{}`.", install_prefix.display());
+        link_opus(is_static, install_prefix, include_dir)?;
+
+        return Ok(LinkedOpus::at(
+            Discovery::InstallPrefix,
+            install_prefix,
+            include_dir,
+        ));
+    }
+
+    if let Some(installed_opus) = find_installed_opus() {
+        link_opus(is_static, &installed_opus, include_dir)?;
+
+        return Ok(LinkedOpus::at(
+            Discovery::PreInstalled,
+            &installed_opus,
+            include_dir,
+        ));
+    }
+
+    #[cfg(feature = "vcpkg")]
+    if is_discovering
+        && let Some(library) = find_via_vcpkg(is_static)
+        && let Some(include_path) = library.include_paths.first()
+    {
+        decision!(
+            "Found `Opus` via `vcpkg` with triplet `{}`.",
+            library.vcpkg_triplet
+        );
+
+        let vcpkg_opus = include_path.parent().unwrap_or(include_path);
+        let include_dir = include_path.join("opus");
+
+        if is_static {
+            link_static_dependencies();
+        }
+
+        println!("cargo:root={}", vcpkg_opus.display());
+        println!("cargo:include={}", include_dir.display());
+
+        return Ok(LinkedOpus::at(Discovery::Vcpkg, vcpkg_opus, &include_dir));
+    }
+
+    // A framework set via `LIBOPUS_FRAMEWORK` is configured explicitly.
+    if target_cfg("target_os") == "macos"
+        && (is_discovering || env_var("LIBOPUS_FRAMEWORK").is_some())
+        && let Some(framework) = find_opus_framework(is_static)
+    {
+        decision!("Found `Opus` as framework at `{}`.", framework.display());
+
+        return Ok(link_opus_framework(&framework));
+    }
+
+    if is_discovering && target_cfg("target_os") == "macos" {
+        if let Some(package_opus) = find_via_macos_package_manager(is_static) {
+            decision!(
+                "Found `Opus` installed by a package manager at `{}`.",
+                package_opus.display()
+            );
+
+            let include_dir = package_opus.join("include").join("opus");
+            link_opus(is_static, &package_opus, &include_dir)?;
+
+            return Ok(LinkedOpus::at(
+                Discovery::PackageManager,
+                &package_opus,
+                &include_dir,
+            ));
+        } else {
+            info!("No `Opus` installed by Homebrew or MacPorts found.");
+        }
+    }
+
+    let opus_build_dir = build_opus(is_static)?;
+
+    Ok(LinkedOpus {
+        discovery: Discovery::BuiltFromSource,
+        library_dir: find_library_dir(&opus_build_dir),
+        include_dir: opus_build_dir.join("include").join("opus"),
+        version: detect_opus_version(&opus_build_dir, &Path::new("opus").join("include")),
+    })
+}
+
+fn run() -> Result<(), BuildError> {
+    emit_rerun_if_changed();
+    print_env_help();
+
+    let install_prefix = find_install_prefix()?;
+    let include_dir = find_include_dir(install_prefix.as_deref());
+    let is_static = is_static_build();
+
+    // Only the declarations are compiled, e.g. to type-check dependent crates.
+    // docs.rs only documents the binding, without the means to build Opus.
+    let is_docs_rs = env_var("DOCS_RS").is_some();
+    let is_linking = !is_docs_rs && env_var("LIBOPUS_NO_LINK").is_none();
+
+    // Without linking, the companion libraries and libclang may be missing,
+    // e.g. on docs.rs.
+    #[cfg(feature = "generate_binding")]
+    if !is_linking {
+        copy_pregenerated_binding()?;
+    } else {
+        let companion_include_dirs = find_companion_libraries(is_static)?;
+        generate_binding(&include_dir, &companion_include_dirs)?;
+    }
+
+    let linked_opus = if is_docs_rs {
+        decision!("Building on docs.rs, not linking Opus.");
+        println!("cargo:include={}", include_dir.display());
+
+        LinkedOpus {
+            discovery: Discovery::NotLinkedOnDocsRs,
+            library_dir: None,
+            version: detect_header_version(&include_dir),
+            include_dir,
+        }
+    } else if !is_linking {
+        println!("cargo:warning=Not linking Opus, binaries using it will fail to link.");
+        println!("cargo:include={}", include_dir.display());
+
+        LinkedOpus {
+            discovery: Discovery::NotLinked,
+            library_dir: None,
+            version: detect_header_version(&include_dir),
+            include_dir,
+        }
     } else {
-        build_opus(is_static);
+        let linked_opus = find_and_link_opus(is_static, &include_dir, install_prefix.as_deref())?;
+
+        // Only an Opus built by this script is known to be position-independent.
+        if is_static
+            && linked_opus.discovery != Discovery::BuiltFromSource
+            && needs_position_independent_code()
+            && is_shared_artifact()
+        {
+            println!(
+                "cargo:warning=Linking a static Opus ({}) into a `cdylib`, which fails with \
+                 relocation errors unless it was built as position-independent code, e.g. via \
+                 `-fPIC`.",
+                linked_opus.discovery
+            );
+        }
+        verify_symbols(&linked_opus, is_static)?;
+
+        linked_opus
+    };
+
+    let opus_version = linked_opus.version.as_deref();
+
+    check_minimum_version(opus_version)?;
+    emit_version_cfgs(opus_version);
+    emit_version_env(opus_version, is_static);
+    emit_linking_cfg(is_static);
+    print_config(&linked_opus, is_static);
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(why) = run() {
+        println!("cargo:warning={}", why);
+        eprintln!("error: {}", why);
+
+        process::exit(1);
     }
 }
@@ -0,0 +1,142 @@
+//! Decides whether Opus is linked statically or dynamically.
+//!
+//! The decision only depends on its [`LinkingInputs`] and the target's
+//! properties, not on Cargo's environment, thus `tests/linking_decision.rs`
+//! verifies it without running the build script.
+
+/// Everything deciding how Opus is linked, gathered by the build script.
+#[derive(Clone, Copy, Debug)]
+pub struct LinkingInputs {
+    /// Whether the target is WebAssembly, which only supports static linking.
+    pub is_wasm: bool,
+    /// Whether `LIBOPUS_STATIC` or `OPUS_STATIC` is set.
+    pub is_static_env: bool,
+    /// Whether `LIBOPUS_DYNAMIC` or `OPUS_DYNAMIC` is set.
+    pub is_dynamic_env: bool,
+    /// Whether the `static`-feature is enabled.
+    pub is_static_feature: bool,
+    /// Whether the `dynamic`-feature is enabled.
+    pub is_dynamic_feature: bool,
+    /// Whether the target links Opus statically by default.
+    pub is_static_by_default: bool,
+}
+
+/// Which of the [`LinkingInputs`] decided how Opus is linked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkingReason {
+    WebAssembly,
+    StaticEnv,
+    DynamicEnv,
+    StaticFeature,
+    DynamicFeature,
+    TargetDefault,
+}
+
+impl LinkingReason {
+    /// Describes the reason for the build output.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::WebAssembly => "WebAssembly only supports static linking.",
+            Self::StaticEnv => "Static environment variable found.",
+            Self::DynamicEnv => "Dynamic environment variable found.",
+            Self::StaticFeature => "Static feature enabled.",
+            Self::DynamicFeature => "Dynamic feature enabled.",
+            Self::TargetDefault => "Linking by the target's default.",
+        }
+    }
+}
+
+/// How Opus is linked, and why.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinkingDecision {
+    /// Whether Opus is linked statically.
+    pub is_static: bool,
+    /// Which input decided the linking.
+    pub reason: LinkingReason,
+    /// Conflicting inputs the build script warns about.
+    pub conflict: Option<&'static str>,
+}
+
+/// Decides whether to link Opus statically, for every combination of
+/// `inputs`.
+///
+/// The precedence, from highest to lowest, is:
+/// 1. WebAssembly targets, which only support static linking.
+/// 2. The environment variables `LIBOPUS_STATIC` or `OPUS_STATIC`, then
+///    `LIBOPUS_DYNAMIC` or `OPUS_DYNAMIC`. If both kinds are set, static
+///    linking wins.
+/// 3. The `static`- or `dynamic`-feature, unless both are enabled.
+/// 4. The target's default.
+pub fn decide_linking(inputs: LinkingInputs) -> LinkingDecision {
+    let decision = |is_static, reason, conflict| LinkingDecision {
+        is_static,
+        reason,
+        conflict,
+    };
+
+    let is_only_static_feature = inputs.is_static_feature && !inputs.is_dynamic_feature;
+    let is_only_dynamic_feature = inputs.is_dynamic_feature && !inputs.is_static_feature;
+
+    if inputs.is_wasm {
+        decision(true, LinkingReason::WebAssembly, None)
+    } else if inputs.is_static_env {
+        let conflict = if inputs.is_dynamic_env {
+            Some("Both a static and a dynamic environment variable are set, linking statically.")
+        } else if is_only_dynamic_feature {
+            Some(
+                "`LIBOPUS_STATIC` or `OPUS_STATIC` overrides the `dynamic`-feature, linking \
+                 statically.",
+            )
+        } else {
+            None
+        };
+
+        decision(true, LinkingReason::StaticEnv, conflict)
+    } else if inputs.is_dynamic_env {
+        let conflict = is_only_static_feature.then_some(
+            "`LIBOPUS_DYNAMIC` or `OPUS_DYNAMIC` overrides the `static`-feature, linking \
+             dynamically.",
+        );
+
+        decision(false, LinkingReason::DynamicEnv, conflict)
+    } else if inputs.is_static_feature && inputs.is_dynamic_feature {
+        decision(
+            inputs.is_static_by_default,
+            LinkingReason::TargetDefault,
+            Some(
+                "Both the `static`- and `dynamic`-feature are enabled, linking by default. \
+                 Set `LIBOPUS_STATIC` or `LIBOPUS_DYNAMIC` to pick one.",
+            ),
+        )
+    } else if inputs.is_static_feature {
+        decision(true, LinkingReason::StaticFeature, None)
+    } else if inputs.is_dynamic_feature {
+        decision(false, LinkingReason::DynamicFeature, None)
+    } else {
+        decision(
+            inputs.is_static_by_default,
+            LinkingReason::TargetDefault,
+            None,
+        )
+    }
+}
+
+/// Returns whether a target links Opus statically by default, given its
+/// `target_os`, `target_env`, and whether it belongs to the `unix` family.
+///
+/// Windows, macOS, Android, Redox, and Linux with musl link statically.
+/// Linux without musl, including big-endian targets such as s390x, the BSDs,
+/// and Windows via MinGW link dynamically. Any other target links statically.
+pub fn is_static_by_default(target_os: &str, target_env: &str, is_unix: bool) -> bool {
+    match target_os {
+        "android" => true,
+        // MinGW toolchains ship Opus as DLL, unlike MSVC ones.
+        "windows" => target_env != "gnu",
+        "macos" | "redox" => true,
+        _ if target_env == "musl" => true,
+        "freebsd" | "openbsd" | "netbsd" | "dragonfly" => false,
+        _ if is_unix && target_env == "gnu" => false,
+        // Other targets rarely provide a system Opus.
+        _ => true,
+    }
+}
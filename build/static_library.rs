@@ -0,0 +1,34 @@
+//! Locates static libraries found via `pkg-config`.
+//!
+//! `pkg-config` omits the system directories from a library's link paths,
+//! leaving them for the linker to search. Whether a static library is
+//! available thus also depends on the package's `libdir`, which
+//! `tests/static_library.rs` verifies without running the build script.
+
+use std::path::{Path, PathBuf};
+
+/// Returns whether `dir` contains the static library `name`, i.e.
+/// `lib<name>.a` or, as built by MSVC, `<name>.lib`.
+pub fn contains_static_library(dir: &Path, name: &str) -> bool {
+    dir.join(format!("lib{}.a", name)).is_file() || dir.join(format!("{}.lib", name)).is_file()
+}
+
+/// Returns the directories `pkg-config` reported in `link_paths`, followed by
+/// the package's `libdir` if it contains a static library of `libs` and is
+/// missing from them, e.g. `/usr/lib/x86_64-linux-gnu`.
+pub fn static_library_dirs(
+    link_paths: &[PathBuf],
+    libdir: Option<&Path>,
+    libs: &[String],
+) -> Vec<PathBuf> {
+    let mut dirs = link_paths.to_vec();
+
+    if let Some(libdir) = libdir
+        && !link_paths.iter().any(|link_path| link_path == libdir)
+        && libs.iter().any(|lib| contains_static_library(libdir, lib))
+    {
+        dirs.push(libdir.to_path_buf());
+    }
+
+    dirs
+}
@@ -0,0 +1,80 @@
+//! Derives settings for building Opus from the target's properties.
+//!
+//! The settings only depend on their arguments, not on Cargo's environment,
+//! thus `tests/target_settings.rs` verifies them without running the build
+//! script.
+
+use std::path::{Path, PathBuf};
+
+/// Maps the Rust target to the ABI name used by the Android NDK.
+pub fn android_abi(target: &str) -> Option<&'static str> {
+    let arch = target.split('-').next()?;
+
+    match arch {
+        "aarch64" => Some("arm64-v8a"),
+        "armv7" | "thumbv7neon" => Some("armeabi-v7a"),
+        "i686" => Some("x86"),
+        "x86_64" => Some("x86_64"),
+        _ => None,
+    }
+}
+
+/// Returns the CMake toolchain file of the Android NDK at `android_ndk`.
+pub fn android_toolchain_file(android_ndk: &Path) -> PathBuf {
+    android_ndk
+        .join("build")
+        .join("cmake")
+        .join("android.toolchain.cmake")
+}
+
+/// Returns the Android platform for the `ANDROID_PLATFORM` set, either e.g.
+/// `android-24` or only the API level `24`, defaulting to `android-21`.
+pub fn android_platform(android_platform: Option<&str>) -> String {
+    match android_platform {
+        Some(platform) if platform.starts_with("android-") => String::from(platform),
+        Some(api_level) => format!("android-{}", api_level),
+        None => String::from("android-21"),
+    }
+}
+
+/// Returns the `-march` vectorising Opus for the RISC-V `target_arch`, e.g.
+/// `rv64gcv`, if `has_feature` reports the vector extension `v`.
+///
+/// The base ISA is extended by the target's other standard extensions, `g`
+/// abbreviating `imafd` along with the CSR and fence extensions.
+pub fn riscv_march(target_arch: &str, has_feature: impl Fn(&str) -> bool) -> Option<String> {
+    let base_isa = match target_arch {
+        "riscv64" => "rv64",
+        "riscv32" => "rv32",
+        _ => return None,
+    };
+
+    if !has_feature("v") {
+        return None;
+    }
+
+    let mut march = String::from(base_isa);
+
+    if ["m", "a", "f", "d"]
+        .iter()
+        .all(|extension| has_feature(extension))
+    {
+        march.push('g');
+    } else {
+        march.push('i');
+        march.extend(
+            ["m", "a", "f", "d"]
+                .iter()
+                .copied()
+                .filter(|extension| has_feature(extension)),
+        );
+    }
+
+    if has_feature("c") {
+        march.push('c');
+    }
+
+    march.push('v');
+
+    Some(march)
+}
@@ -1 +1,15 @@
-#include "../opus/include/opus_multistream.h"
+#include "opus.h"
+#include "opus_multistream.h"
+
+// Set by the build script when the respective feature is enabled.
+#ifdef AUDIOPUS_CUSTOM
+#include "opus_custom.h"
+#endif
+
+#ifdef AUDIOPUS_OPUSFILE
+#include "opusfile.h"
+#endif
+
+#ifdef AUDIOPUS_OPUSENC
+#include "opusenc.h"
+#endif
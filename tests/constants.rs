@@ -0,0 +1,45 @@
+use audiopus_sys::*;
+
+#[test]
+fn error_codes_match_opus() {
+    assert_eq!(OPUS_OK, 0i32);
+    assert_eq!(OPUS_BAD_ARG, -1i32);
+    assert_eq!(OPUS_BUFFER_TOO_SMALL, -2i32);
+    assert_eq!(OPUS_INTERNAL_ERROR, -3i32);
+    assert_eq!(OPUS_INVALID_PACKET, -4i32);
+    assert_eq!(OPUS_UNIMPLEMENTED, -5i32);
+    assert_eq!(OPUS_INVALID_STATE, -6i32);
+    assert_eq!(OPUS_ALLOC_FAIL, -7i32);
+}
+
+#[test]
+fn applications_match_opus() {
+    assert_eq!(OPUS_APPLICATION_VOIP, 2048i32);
+    assert_eq!(OPUS_APPLICATION_AUDIO, 2049i32);
+    assert_eq!(OPUS_APPLICATION_RESTRICTED_LOWDELAY, 2051i32);
+}
+
+#[test]
+fn generic_values_match_opus() {
+    assert_eq!(OPUS_AUTO, -1000i32);
+    assert_eq!(OPUS_BITRATE_MAX, -1i32);
+    assert_eq!(OPUS_SIGNAL_VOICE, 3001i32);
+    assert_eq!(OPUS_SIGNAL_MUSIC, 3002i32);
+    assert_eq!(OPUS_BANDWIDTH_NARROWBAND, 1101i32);
+    assert_eq!(OPUS_BANDWIDTH_MEDIUMBAND, 1102i32);
+    assert_eq!(OPUS_BANDWIDTH_WIDEBAND, 1103i32);
+    assert_eq!(OPUS_BANDWIDTH_SUPERWIDEBAND, 1104i32);
+    assert_eq!(OPUS_BANDWIDTH_FULLBAND, 1105i32);
+}
+
+#[test]
+fn requests_match_opus() {
+    assert_eq!(OPUS_SET_APPLICATION_REQUEST, 4000i32);
+    assert_eq!(OPUS_SET_BITRATE_REQUEST, 4002i32);
+    assert_eq!(OPUS_GET_BITRATE_REQUEST, 4003i32);
+    assert_eq!(OPUS_SET_COMPLEXITY_REQUEST, 4010i32);
+    assert_eq!(OPUS_SET_INBAND_FEC_REQUEST, 4012i32);
+    assert_eq!(OPUS_SET_PACKET_LOSS_PERC_REQUEST, 4014i32);
+    assert_eq!(OPUS_RESET_STATE, 4028i32);
+    assert_eq!(OPUS_GET_SAMPLE_RATE_REQUEST, 4029i32);
+}
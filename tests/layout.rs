@@ -0,0 +1,10 @@
+use std::mem::size_of;
+
+use audiopus_sys::*;
+
+#[test]
+fn states_are_opaque() {
+    assert_eq!(size_of::<OpusEncoder>(), 0);
+    assert_eq!(size_of::<OpusDecoder>(), 0);
+    assert_eq!(size_of::<OpusRepacketizer>(), 0);
+}
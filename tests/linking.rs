@@ -0,0 +1,41 @@
+use std::{ffi::CStr, os::raw::c_int};
+
+use audiopus_sys::*;
+
+#[test]
+fn version_string_is_not_empty() {
+    // SAFETY: Opus returns a pointer to a static, NUL-terminated string.
+    let version = unsafe { CStr::from_ptr(opus_get_version_string()) };
+
+    assert!(!version.to_bytes().is_empty());
+}
+
+#[test]
+fn encoder_is_created_and_destroyed() {
+    let mut error: c_int = 0;
+
+    // SAFETY: `error` is a valid pointer for Opus to write the error code to.
+    let encoder = unsafe { opus_encoder_create(48000, 2, OPUS_APPLICATION_AUDIO, &mut error) };
+
+    assert_eq!(error, OPUS_OK);
+    assert!(!encoder.is_null());
+
+    // SAFETY: `encoder` was created by `opus_encoder_create` and is not used
+    // after being destroyed.
+    unsafe { opus_encoder_destroy(encoder) };
+}
+
+#[test]
+fn linked_version_matches_version_string() {
+    // SAFETY: Opus returns a pointer to a static, NUL-terminated string.
+    let version = unsafe { CStr::from_ptr(opus_get_version_string()) };
+    let version = version.to_string_lossy();
+
+    assert!(matches!(AUDIOPUS_OPUS_LINKING, "static" | "dynamic"));
+
+    // Opus built from a checkout without an exact tag reports `libopus unknown`,
+    // while the build script still detects its version from the headers.
+    if AUDIOPUS_OPUS_VERSION != "unknown" && !version.ends_with("unknown") {
+        assert!(version.contains(AUDIOPUS_OPUS_VERSION));
+    }
+}
@@ -0,0 +1,218 @@
+// The build script's linking decision, verified without running a build.
+#[path = "../build/linking.rs"]
+mod linking;
+
+use linking::{LinkingInputs, LinkingReason, decide_linking};
+
+/// Every combination of the linking inputs, 64 in total.
+fn all_inputs() -> impl Iterator<Item = LinkingInputs> {
+    (0..64u8).map(|bits| LinkingInputs {
+        is_wasm: bits & 1 != 0,
+        is_static_env: bits & 2 != 0,
+        is_dynamic_env: bits & 4 != 0,
+        is_static_feature: bits & 8 != 0,
+        is_dynamic_feature: bits & 16 != 0,
+        is_static_by_default: bits & 32 != 0,
+    })
+}
+
+/// Inputs without WebAssembly, environment variables, or features.
+fn plain_inputs(is_static_by_default: bool) -> LinkingInputs {
+    LinkingInputs {
+        is_wasm: false,
+        is_static_env: false,
+        is_dynamic_env: false,
+        is_static_feature: false,
+        is_dynamic_feature: false,
+        is_static_by_default,
+    }
+}
+
+#[test]
+fn reason_matches_linking() {
+    for inputs in all_inputs() {
+        let decision = decide_linking(inputs);
+
+        let expected_static = match decision.reason {
+            LinkingReason::WebAssembly
+            | LinkingReason::StaticEnv
+            | LinkingReason::StaticFeature => true,
+            LinkingReason::DynamicEnv | LinkingReason::DynamicFeature => false,
+            LinkingReason::TargetDefault => inputs.is_static_by_default,
+        };
+
+        assert_eq!(decision.is_static, expected_static, "{:?}", inputs);
+    }
+}
+
+#[test]
+fn webassembly_always_links_statically() {
+    for inputs in all_inputs().filter(|inputs| inputs.is_wasm) {
+        let decision = decide_linking(inputs);
+
+        assert!(decision.is_static, "{:?}", inputs);
+        assert_eq!(decision.reason, LinkingReason::WebAssembly);
+    }
+}
+
+#[test]
+fn environment_overrides_features() {
+    for inputs in all_inputs().filter(|inputs| !inputs.is_wasm) {
+        let decision = decide_linking(inputs);
+
+        if inputs.is_static_env {
+            assert_eq!(decision.reason, LinkingReason::StaticEnv, "{:?}", inputs);
+        } else if inputs.is_dynamic_env {
+            assert_eq!(decision.reason, LinkingReason::DynamicEnv, "{:?}", inputs);
+        }
+    }
+}
+
+#[test]
+fn single_feature_decides() {
+    for is_static_by_default in [false, true] {
+        let static_feature = LinkingInputs {
+            is_static_feature: true,
+            ..plain_inputs(is_static_by_default)
+        };
+        let dynamic_feature = LinkingInputs {
+            is_dynamic_feature: true,
+            ..plain_inputs(is_static_by_default)
+        };
+
+        assert!(decide_linking(static_feature).is_static);
+        assert!(!decide_linking(dynamic_feature).is_static);
+    }
+}
+
+#[test]
+fn both_features_fall_back_to_default_with_warning() {
+    for is_static_by_default in [false, true] {
+        let decision = decide_linking(LinkingInputs {
+            is_static_feature: true,
+            is_dynamic_feature: true,
+            ..plain_inputs(is_static_by_default)
+        });
+
+        assert_eq!(decision.is_static, is_static_by_default);
+        assert_eq!(decision.reason, LinkingReason::TargetDefault);
+        assert!(decision.conflict.is_some());
+    }
+}
+
+#[test]
+fn neither_feature_uses_default_silently() {
+    for is_static_by_default in [false, true] {
+        let decision = decide_linking(plain_inputs(is_static_by_default));
+
+        assert_eq!(decision.is_static, is_static_by_default);
+        assert_eq!(decision.conflict, None);
+    }
+}
+
+#[test]
+fn conflicts_are_reported() {
+    let both_env = decide_linking(LinkingInputs {
+        is_static_env: true,
+        is_dynamic_env: true,
+        ..plain_inputs(false)
+    });
+    let static_env_dynamic_feature = decide_linking(LinkingInputs {
+        is_static_env: true,
+        is_dynamic_feature: true,
+        ..plain_inputs(false)
+    });
+    let dynamic_env_static_feature = decide_linking(LinkingInputs {
+        is_dynamic_env: true,
+        is_static_feature: true,
+        ..plain_inputs(true)
+    });
+
+    assert!(both_env.is_static && both_env.conflict.is_some());
+    assert!(static_env_dynamic_feature.is_static && static_env_dynamic_feature.conflict.is_some());
+    assert!(!dynamic_env_static_feature.is_static && dynamic_env_static_feature.conflict.is_some());
+}
+
+#[test]
+fn every_reason_is_described() {
+    let descriptions = [
+        (
+            LinkingReason::WebAssembly,
+            "WebAssembly only supports static linking.",
+        ),
+        (
+            LinkingReason::StaticEnv,
+            "Static environment variable found.",
+        ),
+        (
+            LinkingReason::DynamicEnv,
+            "Dynamic environment variable found.",
+        ),
+        (LinkingReason::StaticFeature, "Static feature enabled."),
+        (LinkingReason::DynamicFeature, "Dynamic feature enabled."),
+        (
+            LinkingReason::TargetDefault,
+            "Linking by the target's default.",
+        ),
+    ];
+
+    for (reason, description) in descriptions {
+        assert_eq!(reason.describe(), description);
+    }
+}
+
+#[test]
+fn windows_links_statically_except_mingw() {
+    assert!(linking::is_static_by_default("windows", "msvc", false));
+    assert!(!linking::is_static_by_default("windows", "gnu", false));
+}
+
+#[test]
+fn apple_and_redox_link_statically() {
+    assert!(linking::is_static_by_default("macos", "", true));
+    assert!(linking::is_static_by_default("redox", "relibc", true));
+}
+
+#[test]
+fn linux_links_dynamically_except_musl() {
+    assert!(!linking::is_static_by_default("linux", "gnu", true));
+    assert!(linking::is_static_by_default("linux", "musl", true));
+    assert!(linking::is_static_by_default("android", "", true));
+}
+
+#[test]
+fn bsds_link_dynamically() {
+    for target_os in ["freebsd", "openbsd", "netbsd", "dragonfly"] {
+        assert!(
+            !linking::is_static_by_default(target_os, "", true),
+            "{}",
+            target_os
+        );
+    }
+}
+
+#[test]
+fn other_targets_link_statically() {
+    assert!(linking::is_static_by_default("none", "", false));
+    assert!(linking::is_static_by_default("unknown", "", false));
+    assert!(linking::is_static_by_default("illumos", "", true));
+}
+
+#[test]
+fn env_overrides_target_default_per_platform() {
+    for (target_os, target_env) in [("windows", "msvc"), ("linux", "gnu"), ("macos", "")] {
+        let is_static_by_default = linking::is_static_by_default(target_os, target_env, true);
+
+        let static_env = decide_linking(LinkingInputs {
+            is_static_env: true,
+            ..plain_inputs(is_static_by_default)
+        });
+        let dynamic_env = decide_linking(LinkingInputs {
+            is_dynamic_env: true,
+            ..plain_inputs(is_static_by_default)
+        });
+
+        assert!(static_env.is_static, "{}", target_os);
+        assert!(!dynamic_env.is_static, "{}", target_os);
+    }
+}
@@ -0,0 +1,108 @@
+use std::os::raw::{c_int, c_uchar};
+
+use audiopus_sys::*;
+
+const SAMPLE_RATE: opus_int32 = 48000;
+const CHANNELS: c_int = 2;
+// 20 ms at 48 kHz.
+const FRAME_SIZE: c_int = 960;
+const MAX_PACKET_SIZE: usize = 4000;
+
+#[test]
+fn multistream_encoder_and_decoder_are_created_and_destroyed() {
+    // One coupled stream carrying both channels.
+    let mapping: [c_uchar; 2] = [0, 1];
+    let mut error: c_int = 0;
+
+    // SAFETY: `mapping` holds `CHANNELS` entries and `error` is a valid
+    // pointer for Opus to write the error code to.
+    let encoder = unsafe {
+        opus_multistream_encoder_create(
+            SAMPLE_RATE,
+            CHANNELS,
+            1,
+            1,
+            mapping.as_ptr(),
+            OPUS_APPLICATION_AUDIO,
+            &mut error,
+        )
+    };
+    assert_eq!(error, OPUS_OK);
+    assert!(!encoder.is_null());
+
+    // SAFETY: `mapping` holds `CHANNELS` entries and `error` is a valid
+    // pointer for Opus to write the error code to.
+    let decoder = unsafe {
+        opus_multistream_decoder_create(SAMPLE_RATE, CHANNELS, 1, 1, mapping.as_ptr(), &mut error)
+    };
+    assert_eq!(error, OPUS_OK);
+    assert!(!decoder.is_null());
+
+    // SAFETY: Both were created above and are not used after being destroyed.
+    unsafe {
+        opus_multistream_encoder_destroy(encoder);
+        opus_multistream_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn repacketizer_merges_two_packets() {
+    let silence = vec![0 as opus_int16; FRAME_SIZE as usize];
+    let mut error: c_int = 0;
+
+    // SAFETY: `error` is a valid pointer for Opus to write the error code to.
+    let encoder =
+        unsafe { opus_encoder_create(SAMPLE_RATE, 1, OPUS_APPLICATION_AUDIO, &mut error) };
+    assert_eq!(error, OPUS_OK);
+    assert!(!encoder.is_null());
+
+    let mut packets = [[0; MAX_PACKET_SIZE]; 2];
+    let mut packet_lens = [0; 2];
+
+    for (packet, packet_len) in packets.iter_mut().zip(&mut packet_lens) {
+        // SAFETY: `silence` holds `FRAME_SIZE` samples and `packet` holds
+        // `MAX_PACKET_SIZE` bytes.
+        *packet_len = unsafe {
+            opus_encode(
+                encoder,
+                silence.as_ptr(),
+                FRAME_SIZE,
+                packet.as_mut_ptr(),
+                MAX_PACKET_SIZE as opus_int32,
+            )
+        };
+        assert!(*packet_len > 0);
+    }
+
+    // SAFETY: Creating a repacketizer has no preconditions.
+    let repacketizer = unsafe { opus_repacketizer_create() };
+    assert!(!repacketizer.is_null());
+
+    for (packet, packet_len) in packets.iter().zip(packet_lens) {
+        // SAFETY: `packet` holds `packet_len` valid bytes and outlives the
+        // repacketizer's use of it below.
+        let result = unsafe { opus_repacketizer_cat(repacketizer, packet.as_ptr(), packet_len) };
+        assert_eq!(result, OPUS_OK);
+    }
+
+    // SAFETY: `repacketizer` was created above.
+    assert_eq!(unsafe { opus_repacketizer_get_nb_frames(repacketizer) }, 2);
+
+    let mut merged = [0; MAX_PACKET_SIZE];
+
+    // SAFETY: `merged` holds `MAX_PACKET_SIZE` bytes.
+    let merged_len = unsafe {
+        opus_repacketizer_out(
+            repacketizer,
+            merged.as_mut_ptr(),
+            MAX_PACKET_SIZE as opus_int32,
+        )
+    };
+    assert!(merged_len > 0);
+
+    // SAFETY: Both were created above and are not used after being destroyed.
+    unsafe {
+        opus_repacketizer_destroy(repacketizer);
+        opus_encoder_destroy(encoder);
+    }
+}
@@ -0,0 +1,173 @@
+use std::{f32::consts::PI, os::raw::c_int};
+
+use audiopus_sys::*;
+
+const SAMPLE_RATE: opus_int32 = 48000;
+const CHANNELS: c_int = 1;
+// 20 ms at 48 kHz.
+const FRAME_SIZE: c_int = 960;
+const MAX_PACKET_SIZE: usize = 4000;
+const FREQUENCY: f32 = 440.0;
+const AMPLITUDE: opus_int16 = i16::MAX / 2;
+
+/// A sine wave at `FREQUENCY` of `len` samples.
+fn sine_wave(len: usize) -> Vec<opus_int16> {
+    (0..len)
+        .map(|sample| {
+            let time = sample as f32 / SAMPLE_RATE as f32;
+
+            ((2.0 * PI * FREQUENCY * time).sin() * f32::from(AMPLITUDE)) as opus_int16
+        })
+        .collect()
+}
+
+#[test]
+fn sine_wave_survives_encode_and_decode() {
+    let input = sine_wave(FRAME_SIZE as usize);
+
+    let mut error: c_int = 0;
+
+    // SAFETY: `error` is a valid pointer for Opus to write the error code to.
+    let encoder =
+        unsafe { opus_encoder_create(SAMPLE_RATE, CHANNELS, OPUS_APPLICATION_AUDIO, &mut error) };
+    assert_eq!(error, OPUS_OK);
+    assert!(!encoder.is_null());
+
+    // SAFETY: `error` is a valid pointer for Opus to write the error code to.
+    let decoder = unsafe { opus_decoder_create(SAMPLE_RATE, CHANNELS, &mut error) };
+    assert_eq!(error, OPUS_OK);
+    assert!(!decoder.is_null());
+
+    let mut packet = [0u8; MAX_PACKET_SIZE];
+
+    // SAFETY: `input` holds `FRAME_SIZE` samples for the single channel and
+    // `packet` is valid for writing `MAX_PACKET_SIZE` bytes.
+    let packet_len = unsafe {
+        opus_encode(
+            encoder,
+            input.as_ptr(),
+            FRAME_SIZE,
+            packet.as_mut_ptr(),
+            MAX_PACKET_SIZE as opus_int32,
+        )
+    };
+    assert!(packet_len > 0, "encoding failed with {}", packet_len);
+
+    let mut output = vec![0 as opus_int16; FRAME_SIZE as usize];
+
+    // SAFETY: `packet` holds `packet_len` encoded bytes and `output` is valid
+    // for writing `FRAME_SIZE` samples for the single channel.
+    let decoded_samples = unsafe {
+        opus_decode(
+            decoder,
+            packet.as_ptr(),
+            packet_len,
+            output.as_mut_ptr(),
+            FRAME_SIZE,
+            0,
+        )
+    };
+    assert_eq!(decoded_samples, FRAME_SIZE);
+
+    // SAFETY: Both were created above and are not used after being destroyed.
+    unsafe {
+        opus_encoder_destroy(encoder);
+        opus_decoder_destroy(decoder);
+    }
+}
+
+// Samples are exchanged as native-endian integers. Byte-swapped samples turn
+// the sine wave into noise, which this catches on big-endian targets such as
+// s390x, unlike comparing sizes or byte patterns.
+#[test]
+fn decoded_sine_wave_keeps_its_frequency_and_amplitude() {
+    const FRAMES: usize = 10;
+    // The last frames, after the encoder has settled.
+    const CHECKED_FRAMES: usize = 5;
+
+    let input = sine_wave(FRAMES * FRAME_SIZE as usize);
+
+    let mut error: c_int = 0;
+
+    // SAFETY: `error` is a valid pointer for Opus to write the error code to.
+    let encoder =
+        unsafe { opus_encoder_create(SAMPLE_RATE, CHANNELS, OPUS_APPLICATION_AUDIO, &mut error) };
+    assert_eq!(error, OPUS_OK);
+    assert!(!encoder.is_null());
+
+    // SAFETY: `error` is a valid pointer for Opus to write the error code to.
+    let decoder = unsafe { opus_decoder_create(SAMPLE_RATE, CHANNELS, &mut error) };
+    assert_eq!(error, OPUS_OK);
+    assert!(!decoder.is_null());
+
+    let mut output = vec![0 as opus_int16; input.len()];
+    let mut packet = [0u8; MAX_PACKET_SIZE];
+
+    for (input_frame, output_frame) in input
+        .chunks_exact(FRAME_SIZE as usize)
+        .zip(output.chunks_exact_mut(FRAME_SIZE as usize))
+    {
+        // SAFETY: `input_frame` holds `FRAME_SIZE` samples for the single
+        // channel and `packet` is valid for writing `MAX_PACKET_SIZE` bytes.
+        let packet_len = unsafe {
+            opus_encode(
+                encoder,
+                input_frame.as_ptr(),
+                FRAME_SIZE,
+                packet.as_mut_ptr(),
+                MAX_PACKET_SIZE as opus_int32,
+            )
+        };
+        assert!(packet_len > 0, "encoding failed with {}", packet_len);
+
+        // SAFETY: `packet` holds `packet_len` encoded bytes and `output_frame`
+        // is valid for writing `FRAME_SIZE` samples for the single channel.
+        let decoded_samples = unsafe {
+            opus_decode(
+                decoder,
+                packet.as_ptr(),
+                packet_len,
+                output_frame.as_mut_ptr(),
+                FRAME_SIZE,
+                0,
+            )
+        };
+        assert_eq!(decoded_samples, FRAME_SIZE);
+    }
+
+    // SAFETY: Both were created above and are not used after being destroyed.
+    unsafe {
+        opus_encoder_destroy(encoder);
+        opus_decoder_destroy(decoder);
+    }
+
+    let checked = &output[(FRAMES - CHECKED_FRAMES) * FRAME_SIZE as usize..];
+
+    // A sine wave crosses zero twice per period.
+    let zero_crossings = checked
+        .windows(2)
+        .filter(|pair| (pair[0] < 0) != (pair[1] < 0))
+        .count();
+    let duration = checked.len() as f32 / SAMPLE_RATE as f32;
+    let expected_crossings = (2.0 * FREQUENCY * duration) as usize;
+
+    assert!(
+        zero_crossings.abs_diff(expected_crossings) <= expected_crossings / 10,
+        "expected about {} zero crossings, found {}",
+        expected_crossings,
+        zero_crossings
+    );
+
+    let peak = checked
+        .iter()
+        .map(|sample| sample.unsigned_abs())
+        .max()
+        .unwrap_or_default();
+
+    assert!(
+        peak.abs_diff(AMPLITUDE.unsigned_abs()) <= AMPLITUDE.unsigned_abs() / 4,
+        "expected a peak of about {}, found {}",
+        AMPLITUDE,
+        peak
+    );
+}
@@ -0,0 +1,67 @@
+// Locating a static Opus found via `pkg-config`, verified without running a build.
+#[path = "../build/static_library.rs"]
+mod static_library;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use static_library::static_library_dirs;
+
+/// Creates a fresh directory `name` containing the empty `files`.
+fn directory(name: &str, files: &[&str]) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("static_library")
+        .join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    for file in files {
+        fs::write(dir.join(file), []).unwrap();
+    }
+
+    dir
+}
+
+fn opus() -> Vec<String> {
+    vec![String::from("opus")]
+}
+
+#[test]
+fn library_in_system_directory_is_found() {
+    // `pkg-config` reports no link path for Opus in a system directory.
+    let libdir = directory("system", &["libopus.a", "libopus.so"]);
+
+    assert_eq!(
+        static_library_dirs(&[], Some(&libdir), &opus()),
+        vec![libdir]
+    );
+}
+
+#[test]
+fn msvc_library_in_system_directory_is_found() {
+    let libdir = directory("msvc", &["opus.lib"]);
+
+    assert_eq!(
+        static_library_dirs(&[], Some(&libdir), &opus()),
+        vec![libdir]
+    );
+}
+
+#[test]
+fn system_directory_without_static_library_is_ignored() {
+    let libdir = directory("shared", &["libopus.so"]);
+
+    assert!(static_library_dirs(&[], Some(&libdir), &opus()).is_empty());
+}
+
+#[test]
+fn reported_link_path_is_kept_once() {
+    let libdir = directory("reported", &["libopus.a"]);
+
+    assert_eq!(
+        static_library_dirs(std::slice::from_ref(&libdir), Some(&libdir), &opus()),
+        vec![libdir]
+    );
+}
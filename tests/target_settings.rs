@@ -0,0 +1,79 @@
+// The build script's target-specific settings, verified without running a build.
+#[path = "../build/target_settings.rs"]
+mod target_settings;
+
+use std::path::Path;
+
+use target_settings::{android_abi, android_platform, android_toolchain_file, riscv_march};
+
+/// Reports the features in `features` as enabled.
+fn enabled(features: &'static [&'static str]) -> impl Fn(&str) -> bool {
+    move |feature| features.contains(&feature)
+}
+
+#[test]
+fn riscv_vectors_extend_general_purpose_isa() {
+    // E.g. `riscv64gc-unknown-linux-gnu` with `-C target-feature=+v`.
+    assert_eq!(
+        riscv_march("riscv64", enabled(&["m", "a", "f", "d", "c", "v"])).as_deref(),
+        Some("rv64gcv")
+    );
+    assert_eq!(
+        riscv_march("riscv32", enabled(&["m", "a", "f", "d", "c", "v"])).as_deref(),
+        Some("rv32gcv")
+    );
+}
+
+#[test]
+fn riscv_lists_partial_extensions() {
+    assert_eq!(
+        riscv_march("riscv32", enabled(&["m", "c", "v"])).as_deref(),
+        Some("rv32imcv")
+    );
+    assert_eq!(
+        riscv_march("riscv64", enabled(&["m", "a", "v"])).as_deref(),
+        Some("rv64imav")
+    );
+}
+
+#[test]
+fn riscv_without_vectors_is_left_alone() {
+    assert_eq!(
+        riscv_march("riscv64", enabled(&["m", "a", "f", "d", "c"])),
+        None
+    );
+}
+
+#[test]
+fn other_architectures_are_left_alone() {
+    assert_eq!(riscv_march("x86_64", enabled(&["v"])), None);
+    assert_eq!(riscv_march("aarch64", enabled(&["v"])), None);
+}
+
+#[test]
+fn android_abi_follows_architecture() {
+    assert_eq!(android_abi("aarch64-linux-android"), Some("arm64-v8a"));
+    assert_eq!(android_abi("armv7-linux-androideabi"), Some("armeabi-v7a"));
+    assert_eq!(
+        android_abi("thumbv7neon-linux-androideabi"),
+        Some("armeabi-v7a")
+    );
+    assert_eq!(android_abi("i686-linux-android"), Some("x86"));
+    assert_eq!(android_abi("x86_64-linux-android"), Some("x86_64"));
+    assert_eq!(android_abi("riscv64-linux-android"), None);
+}
+
+#[test]
+fn android_toolchain_file_is_inside_ndk() {
+    assert_eq!(
+        android_toolchain_file(Path::new("ndk")),
+        Path::new("ndk/build/cmake/android.toolchain.cmake")
+    );
+}
+
+#[test]
+fn android_platform_accepts_api_level() {
+    assert_eq!(android_platform(Some("android-24")), "android-24");
+    assert_eq!(android_platform(Some("24")), "android-24");
+    assert_eq!(android_platform(None), "android-21");
+}